@@ -1,10 +1,16 @@
 #ifndef PRELUDE_HPP
 #define PRELUDE_HPP
 
-#include <cassert>
+#include <cstdint>
+#include <cstdlib>
 #include <array>
+#include <bit>
+#include <concepts>
+#include <limits>
+#include <type_traits>
 #include <deque>
 #include <iostream>
+#include <sstream>
 #include <string>
 #include <utility>
 #include <ranges>
@@ -12,45 +18,258 @@
 #include <boost/multiprecision/cpp_int.hpp>
 
 using Int = boost::multiprecision::cpp_int;
-using List = std::deque<Int>;
-template <std::size_t N>
-using Array = std::array<Int, N>;
+template <class T>
+using ListOf = std::deque<T>;
+using List = ListOf<Int>;
+template <std::size_t N, class T = Int>
+using Array = std::array<T, N>;
 
 template <class> struct S;
-template <> struct S<List> {
-    using Nonsense = void*;
-    static constexpr const char* name() noexcept { return "list"; }
-};
-template <std::size_t N> struct S<Array<N>> {
+template <class T> struct S<ListOf<T>> {
     using Nonsense = void*;
-    static std::string name() { return "array<" + std::to_string(N) + ">"; }
+    static std::string name() {
+        if constexpr (std::is_same_v<T, Int>) {
+            return "list";
+        } else {
+            return "list<" + std::string(S<T>::name()) + ">";
+        }
+    }
 };
 template <> struct S<Int> {
     static constexpr const char* name() noexcept { return "int"; }
 };
 
+template <class T>
+const T& show(const T& x) {
+    return x;
+}
+
+// begin: fixed
+using u8 = std::uint8_t;
+using u16 = std::uint16_t;
+using u32 = std::uint32_t;
+using u64 = std::uint64_t;
+using i8 = std::int8_t;
+using i16 = std::int16_t;
+using i32 = std::int32_t;
+using i64 = std::int64_t;
+
+template <> struct S<u8> { static constexpr const char* name() noexcept { return "u8"; } };
+template <> struct S<u16> { static constexpr const char* name() noexcept { return "u16"; } };
+template <> struct S<u32> { static constexpr const char* name() noexcept { return "u32"; } };
+template <> struct S<u64> { static constexpr const char* name() noexcept { return "u64"; } };
+template <> struct S<i8> { static constexpr const char* name() noexcept { return "i8"; } };
+template <> struct S<i16> { static constexpr const char* name() noexcept { return "i16"; } };
+template <> struct S<i32> { static constexpr const char* name() noexcept { return "i32"; } };
+template <> struct S<i64> { static constexpr const char* name() noexcept { return "i64"; } };
+
+inline int show(u8 x) { return x; }
+inline int show(i8 x) { return x; }
+
+template <std::integral T, std::integral U>
+T wrap(U x) {
+    return static_cast<T>(x);
+}
+
+template <std::integral T>
+T wrap(const Int& x) {
+    using U = std::make_unsigned_t<T>;
+    const Int modulus = Int(1) << std::numeric_limits<U>::digits;
+    Int r = x % modulus;
+    if (r < 0) r += modulus;
+    return static_cast<T>(r.template convert_to<U>());
+}
+
+// `l op r` of width T, computed in u64 or Int so that it wraps instead of overflowing
+template <std::integral T, class L, class R>
+T wrapping_add(const L& l, const R& r) {
+    return static_cast<T>(static_cast<u64>(wrap<T>(l)) + static_cast<u64>(wrap<T>(r)));
+}
+
+template <std::integral T, class L, class R>
+T wrapping_sub(const L& l, const R& r) {
+    return static_cast<T>(static_cast<u64>(wrap<T>(l)) - static_cast<u64>(wrap<T>(r)));
+}
+
+template <std::integral T, class L, class R>
+T wrapping_mul(const L& l, const R& r) {
+    return static_cast<T>(static_cast<u64>(wrap<T>(l)) * static_cast<u64>(wrap<T>(r)));
+}
+
+template <std::integral T, class L, class R>
+T wrapping_div(const L& l, const R& r) {
+    return wrap<T>(Int(wrap<T>(l)) / Int(wrap<T>(r)));
+}
+
+template <std::integral T, class L, class R>
+T wrapping_rem(const L& l, const R& r) {
+    return wrap<T>(Int(wrap<T>(l)) % Int(wrap<T>(r)));
+}
+
+template <std::integral T, class E>
+void add_wrapping(T& x, const E& e) {
+    x = static_cast<T>(static_cast<u64>(x) + static_cast<u64>(wrap<T>(e)));
+}
+
+template <std::integral T, class E>
+void sub_wrapping(T& x, const E& e) {
+    x = static_cast<T>(static_cast<u64>(x) - static_cast<u64>(wrap<T>(e)));
+}
+
+template <std::integral T, class E>
+void xor_wrapping(T& x, const E& e) {
+    x = static_cast<T>(x ^ wrap<T>(e));
+}
+
+template <std::integral T, class E>
+void mul_wrapping(T& x, const E& e) {
+    x = static_cast<T>(static_cast<u64>(x) * static_cast<u64>(wrap<T>(e)));
+}
+
+template <std::integral T, class E>
+void rotl(T& x, const E& k) {
+    using U = std::make_unsigned_t<T>;
+    const int s = (Int(k) % std::numeric_limits<U>::digits).template convert_to<int>();
+    x = static_cast<T>(std::rotl(static_cast<U>(x), s));
+}
+
+template <std::integral T, class E>
+void rotr(T& x, const E& k) {
+    using U = std::make_unsigned_t<T>;
+    const int s = (Int(k) % std::numeric_limits<U>::digits).template convert_to<int>();
+    x = static_cast<T>(std::rotr(static_cast<U>(x), s));
+}
+
+template <std::integral T>
+void complement(T& x) {
+    x = static_cast<T>(~x);
+}
+
+// x /= e undoes x *= e: multiplies by the inverse of the odd e modulo 2^n
+template <std::integral T, class E>
+void div_wrapping(T& x, const E& e) {
+    const u64 a = static_cast<u64>(wrap<T>(e));
+    u64 inverse = a;
+    for (int i = 0; i < 5; ++i) inverse *= 2 - a * inverse;
+    x = static_cast<T>(static_cast<u64>(x) * inverse);
+}
+// end: fixed
+
 template <class T, typename S<T>::Nonsense = nullptr>
 std::ostream& operator<<(std::ostream& os, const T& l) {
     const char* delim = "";
     os << '[';
     for (const auto& x : l) {
-        os << std::exchange(delim, ", ") << x;
+        os << std::exchange(delim, ", ") << show(x);
     }
     return os << ']';
 }
 
+// begin: array
+template <std::size_t N, class T> struct S<Array<N, T>> {
+    using Nonsense = void*;
+    static std::string name() {
+        if constexpr (std::is_same_v<T, Int>) {
+            return "array<" + std::to_string(N) + ">";
+        } else {
+            return "array<" + std::to_string(N) + ", " + S<T>::name() + ">";
+        }
+    }
+};
+// end: array
+
+// begin: proc
+template <class... Ts> struct Proc {
+    void (*fwd)(Ts...);
+    void (*rev)(Ts...);
+};
+// end: proc
+
+// begin: site
+struct Site {
+    const char* proc;
+    const char* direction;
+    const char* file;
+    int line;
+};
+
+[[noreturn]] inline void fail(const Site& site, const std::string& message) {
+    std::cerr << site.file << ':' << site.line << ": " << message
+              << " (in " << site.proc << ", " << site.direction << ")\n";
+    std::abort();
+}
+// end: site
+
+// begin: expect
+template <class T, class U>
+void expect_eq(const T& actual, const U& expected, const char* what, const char* text, const Site& site) {
+    if (actual == expected) return;
+    std::ostringstream os;
+    os << what << " expected " << text << " (= " << show(expected) << "), got " << show(actual);
+    fail(site, os.str());
+}
+
+inline void where(std::ostream&, const char*) {}
+
+// prints the operands of a failed condition, given as name and value pairs
+template <class V, class... Vs>
+void where(std::ostream& os, const char* delim, const char* name, const V& value, const Vs&... rest) {
+    os << delim << name << " = " << show(value);
+    where(os, ", ", rest...);
+}
+
+template <class C, class... Vs>
+void expect(const C& condition, bool expected, const char* what, const char* text, const Site& site,
+            const Vs&... operands) {
+    if (static_cast<bool>(condition) == expected) return;
+    std::ostringstream os;
+    os << what << " expected `" << text << "` to be " << std::boolalpha << expected << ", got " << !expected;
+    where(os, ", where ", operands...);
+    fail(site, os.str());
+}
+// end: expect
+
 template <class T>
-const Int& index(const T& l, const Int& i) {
+auto& index(T& l, const Int& i) {
     return l[i.convert_to<std::size_t>()];
 }
 
+// begin: checked_index
 template <class T>
-Int& index(T& l, const Int& i) {
+auto& index_checked(T& l, const Int& i, const char* name, const Site& site) {
+    if (i < 0 || i >= l.size()) {
+        std::ostringstream os;
+        os << "index " << i << " out of bounds for `" << name << "` of size " << l.size();
+        fail(site, os.str());
+    }
     return l[i.convert_to<std::size_t>()];
 }
 
+template <class T>
+void reverse_range_checked(T& l, const Int& from, const Int& to, const char* name, const Site& site) {
+    if (from < 0 || from > to || to > l.size()) {
+        std::ostringstream os;
+        os << "range " << from << ".." << to << " out of bounds for `" << name << "` of size " << l.size();
+        fail(site, os.str());
+    }
+    std::reverse(l.begin() + from.convert_to<std::ptrdiff_t>(), l.begin() + to.convert_to<std::ptrdiff_t>());
+}
+// end: checked_index
+
+// begin: narrow
+template <std::integral T>
+T narrow(const Int& x, const Site& site) {
+    if (x < std::numeric_limits<T>::min() || x > std::numeric_limits<T>::max()) {
+        std::ostringstream os;
+        os << "conversion of " << x << " to " << S<T>::name() << " is out of range";
+        fail(site, os.str());
+    }
+    return x.template convert_to<T>();
+}
+// end: narrow
+
 template <class T> void print(const char* name, const T& target) {
-    std::cout << name << ": " << S<T>::name() << " = " << target << '\n';
+    std::cout << name << ": " << S<T>::name() << " = " << show(target) << '\n';
 }
 
 template <class T>
@@ -58,6 +277,69 @@ void swap(T&& l, T&& r) {
     std::swap(std::forward<T>(l), std::forward<T>(r));
 }
 
+// begin: muldiv
+template <class T, class E>
+void mul_exact(T& x, const E& e, const Site& site) {
+    if constexpr (std::integral<T>) {
+        if (!(wrap<T>(e) & 1)) {
+            std::ostringstream os;
+            os << "multiplier " << show(e) << " of " << S<T>::name() << " is not odd";
+            fail(site, os.str());
+        }
+        mul_wrapping(x, e);
+    } else {
+        if (e == 0) fail(site, "multiplication by zero");
+        x *= e;
+    }
+}
+
+template <class T, class E>
+void div_exact(T& x, const E& e, const Site& site) {
+    if constexpr (std::integral<T>) {
+        if (!(wrap<T>(e) & 1)) {
+            std::ostringstream os;
+            os << "divisor " << show(e) << " of " << S<T>::name() << " is not odd";
+            fail(site, os.str());
+        }
+        div_wrapping(x, e);
+    } else {
+        if (e == 0) fail(site, "division by zero");
+        if (x % e != 0) {
+            std::ostringstream os;
+            os << x << " is not divisible by " << show(e);
+            fail(site, os.str());
+        }
+        x /= e;
+    }
+}
+// end: muldiv
+
+// begin: reverse
+template <class T>
+void reverse_range(T& l, const Int& from, const Int& to) {
+    std::reverse(l.begin() + from.convert_to<std::ptrdiff_t>(), l.begin() + to.convert_to<std::ptrdiff_t>());
+}
+// end: reverse
+
+// begin: negate
+template <class T>
+void negate(T& x) {
+    if constexpr (requires { x.begin(); }) {
+        for (auto& y : x) negate(y);
+    } else {
+        x = static_cast<T>(-x);
+    }
+}
+// end: negate
+
+// begin: cswap
+template <class C, class T>
+void cswap(const C& c, T& l, T& r) {
+    if (c != 0) std::swap(l, r);
+}
+// end: cswap
+
+// begin: perm
 template <class T>
 bool is_valid_perm(const T& l) {
     T copied = l;
@@ -70,10 +352,15 @@ bool is_valid_perm(const T& l) {
 }
 
 template <class T, class U>
-void assert_valid_perm(const T& c, const U& p) {
-    assert(("not a valid permutation", c.size() == p.size() && is_valid_perm(p)));
+void assert_valid_perm(const T& c, const U& p, const char* c_name, const char* p_name, const Site& site) {
+    if (c.size() == p.size() && is_valid_perm(p)) return;
+    std::ostringstream os;
+    os << "`" << p_name << "` is not a permutation of the indices of `" << c_name << "`";
+    fail(site, os.str());
 }
+// end: perm
 
+// begin: iota
 // inj iota(const int n, list dst)
 //     local int i = 0
 //         from i = 0
@@ -84,54 +371,71 @@ void assert_valid_perm(const T& c, const U& p) {
 //             i += 1
 //         until i = n
 //     delocal int i = n
-// 
-// inj indices(const list src, list dst)
-//     local const int n = size(src)
-//         call iota(n, dst)
-//     delocal const int n = size(src)
+
+// the assertions of the library procs, which --unchecked omits by defining INVL_UNCHECKED
+template <class... Ts>
+void expect_library(const Ts&... args) {
+#ifndef INVL_UNCHECKED
+    expect(args...);
+#endif
+}
+
+template <class... Ts>
+void expect_eq_library(const Ts&... args) {
+#ifndef INVL_UNCHECKED
+    expect_eq(args...);
+#endif
+}
 
 void iota_fwd(const Int& n, List& dst) {
     {
         Int i = 0;
-        assert(i == 0);
+        expect_library(i == 0, true, "from", "i = 0", Site{"iota", "fwd", __FILE__, __LINE__}, "i", i);
         while (!(i == n)) {
             {
                 Int x = i;
                 dst.push_back(x);
                 x = 0;
-                assert(x == 0);
+                expect_eq_library(x, 0, "delocal of `x`", "0", Site{"iota", "fwd", __FILE__, __LINE__});
             }
             i += 1;
-            assert(!(i == 0));
+            expect_library(i == 0, false, "from", "i = 0", Site{"iota", "fwd", __FILE__, __LINE__}, "i", i);
         }
-        assert(i == n);
+        expect_eq_library(i, n, "delocal of `i`", "n", Site{"iota", "fwd", __FILE__, __LINE__});
     }
 }
 
 void iota_rev(const Int& n, List& dst) {
     {
         Int i = n;
-        assert(i == n);
+        expect_library(i == n, true, "from", "i = n", Site{"iota", "rev", __FILE__, __LINE__}, "i", i, "n", n);
         while (!(i == 0)) {
             i -= 1;
             {
                 Int x = 0;
-                assert(x == 0);
+                expect_eq_library(x, 0, "pop_back into `x`", "0", Site{"iota", "rev", __FILE__, __LINE__});
                 x = dst.back();
                 dst.pop_back();
-                assert(x == i);
+                expect_eq_library(x, i, "delocal of `x`", "i", Site{"iota", "rev", __FILE__, __LINE__});
             }
-            assert(!(i == n));
+            expect_library(i == n, false, "from", "i = n", Site{"iota", "rev", __FILE__, __LINE__}, "i", i, "n", n);
         }
-        assert(i == 0);
+        expect_eq_library(i, 0, "delocal of `i`", "0", Site{"iota", "rev", __FILE__, __LINE__});
     }
 }
+// end: iota
+
+// begin: indices
+// inj indices(const list src, list dst)
+//     local const int n = size(src)
+//         call iota(n, dst)
+//     delocal const int n = size(src)
 
 void indices_fwd(const List& src, List& dst) {
     {
         const Int n = src.size();
         iota_fwd(n, dst);
-        assert(n == src.size());
+        expect_eq_library(n, src.size(), "delocal of `n`", "size(src)", Site{"indices", "fwd", __FILE__, __LINE__});
     }
 }
 
@@ -139,8 +443,9 @@ void indices_rev(const List& src, List& dst) {
     {
         const Int n = src.size();
         iota_rev(n, dst);
-        assert(n == src.size());
+        expect_eq_library(n, src.size(), "delocal of `n`", "size(src)", Site{"indices", "rev", __FILE__, __LINE__});
     }
 }
+// end: indices
 
 #endif
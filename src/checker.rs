@@ -1,23 +1,29 @@
 mod detail;
 
 use crate::parser::{
-    detail::{MainProc, Proc, ProcId, Program, Statement, TypedVariable, Variable},
-    r#for::For,
+    case::Case,
+    detail::{
+        Alias, Expr, Function, InnerType, MainProc, Place, Proc, ProcId, ProcKind, Program,
+        Statement, Type, TypedVariable, Variable,
+    },
+    r#for::{For, Range},
+};
+use detail::{
+    CheckMut, CheckType, HasVariable, TypeOf, assert_assignable, check_call, mentions_elements,
+};
+use std::{
+    collections::{BTreeMap, LinkedList},
+    rc::Rc,
 };
-use detail::{CheckMut, HasVariable};
-use std::collections::{BTreeMap, BTreeSet};
 
 type Mutables = BTreeMap<Variable, bool>;
-
-#[derive(Debug)]
-enum ProcType {
-    Inj,
-    Invl,
-}
+type Types = BTreeMap<Variable, Type>;
+type Signatures = BTreeMap<ProcId, (ProcKind, LinkedList<Type>)>;
 
 #[derive(Debug)]
 pub struct Checker {
-    proc_types: BTreeMap<ProcId, ProcType>,
+    signatures: Signatures,
+    functions: Types,
 }
 
 impl Checker {
@@ -26,33 +32,70 @@ impl Checker {
     }
 
     fn new(program: &Program) -> Self {
-        let Program(_, procs) = program;
-        let mut proc_types = BTreeMap::<ProcId, ProcType>::new();
+        let Program(_, procs, _, functions) = program;
+        let mut signatures = Signatures::new();
         for proc in procs {
-            if let (id, Some(_)) = match proc {
-                Proc::Inj(id, _, _) => (id, proc_types.insert(id.clone(), ProcType::Inj)),
-                Proc::Invl(id, _, _, _) | Proc::Mat(id, _) => {
-                    (id, proc_types.insert(id.clone(), ProcType::Invl))
-                }
-            } {
+            let id = proc.id();
+            if signatures
+                .insert(id.clone(), (proc.kind(), proc.signature()))
+                .is_some()
+            {
                 panic!("colliding function names: {}", id.0);
             }
         }
-        Self { proc_types }
+
+        let mut types = Types::new();
+        for Function(q, params, TypedVariable(t, _), _) in functions {
+            let params = params.iter().map(|TypedVariable(t, _)| t.clone()).collect();
+            let result = match t.inner {
+                InnerType::Scalar(s) => s,
+                _ => panic!("fn `{}` must return an int or a fixed-width integer", q.0),
+            };
+            let t = Type {
+                r#const: true,
+                inner: InnerType::Fn(Rc::new(params), result),
+            };
+            if signatures.contains_key(q) || types.insert(Variable(q.0.clone()), t).is_some() {
+                panic!("colliding function names: {}", q.0);
+            }
+        }
+        Self {
+            signatures,
+            functions: types,
+        }
+    }
+
+    /// The signatures seen from the body of a proc, where its proc parameters shadow
+    /// the global procs.
+    fn local(&self, params: &LinkedList<TypedVariable>) -> Self {
+        let mut signatures = self.signatures.clone();
+        for TypedVariable(t, f) in params {
+            if let InnerType::Proc(kind, params) = &t.inner {
+                let params = params.as_ref().clone();
+                signatures.insert(ProcId(f.0.clone()), (*kind, params));
+            }
+        }
+        Self {
+            signatures,
+            functions: self.functions.clone(),
+        }
     }
 
     fn check_program(&self, program: &Program) {
-        let Program(main, procs) = program;
+        let Program(main, procs, _, functions) = program;
         self.check_main(main);
         for proc in procs {
             self.check_proc(proc);
         }
+        for function in functions {
+            self.check_function(function);
+        }
     }
 
     fn assert_invl(&self, id: &ProcId) {
-        match self.proc_types.get(id) {
-            Some(ProcType::Inj) => panic!("expected invl, found inj"),
-            Some(ProcType::Invl) => {}
+        match self.signatures.get(id) {
+            Some((ProcKind::Inj, _)) => panic!("expected invl, found inj"),
+            Some((ProcKind::Invl, _)) => {}
             None => panic!("undefined function found: {:?}", id.0),
         }
     }
@@ -60,11 +103,42 @@ impl Checker {
     fn ban_inj_call(&self, invl: &Statement) {
         match invl {
             Statement::Call(id, _) | Statement::Uncall(id, _) => self.assert_invl(id),
-            Statement::For(For { statement, .. }) => self.ban_inj_call(statement),
+            Statement::For(For { statement, .. })
+            | Statement::ForRange(Range { statement, .. })
+            | Statement::Located(_, statement) => self.ban_inj_call(statement),
             Statement::IfThenElse(_, s_l, s_r) | Statement::Sequence(s_l, s_r) => {
                 self.ban_inj_call(s_l);
                 self.ban_inj_call(s_r);
             }
+            Statement::InvlBlock(_, i) => self.ban_inj_call(i),
+            _ => {}
+        }
+    }
+
+    fn check_invl_blocks(&self, statement: &Statement) {
+        match statement {
+            Statement::InvlBlock(s, i) => {
+                self.ban_inj_call(i);
+                self.check_invl_blocks(s);
+                self.check_invl_blocks(i);
+            }
+            Statement::IfThenElseFi(_, s_l, s_r, _)
+            | Statement::FromDoLoopUntil(_, s_l, s_r, _)
+            | Statement::IfThenElse(_, s_l, s_r)
+            | Statement::Sequence(s_l, s_r) => {
+                self.check_invl_blocks(s_l);
+                self.check_invl_blocks(s_r);
+            }
+            Statement::Case(Case { arms, default, .. }) => {
+                for (_, s) in arms {
+                    self.check_invl_blocks(s);
+                }
+                self.check_invl_blocks(default);
+            }
+            Statement::LocalDelocal(_, _, s, _, _)
+            | Statement::For(For { statement: s, .. })
+            | Statement::ForRange(Range { statement: s, .. })
+            | Statement::Located(_, s) => self.check_invl_blocks(s),
             _ => {}
         }
     }
@@ -75,51 +149,203 @@ impl Checker {
     {
         let mut mutables = Mutables::new();
 
-        for TypedVariable(t, x) in variables {
+        for (x, t) in variables.into_iter().flat_map(TypedVariable::bindings) {
             if !t.r#const {
-                mutables.insert(x.clone(), false);
+                mutables.insert(x, false);
             }
         }
 
         mutables
     }
 
+    fn types<'a, I>(variables: I) -> Types
+    where
+        I: IntoIterator<Item = &'a TypedVariable>,
+    {
+        variables
+            .into_iter()
+            .flat_map(TypedVariable::bindings)
+            .collect()
+    }
+
+    /// The fns, and the parameters of a proc along with its size parameters as `const int`s.
+    fn scope(&self, sizes: &LinkedList<Variable>, params: &LinkedList<TypedVariable>) -> Types {
+        let mut types = self.functions.clone();
+        types.extend(Self::types(params));
+        for n in sizes {
+            types.insert(n.clone(), Type::CONST_INT);
+        }
+        types
+    }
+
+    fn check_init(t: &InnerType, e: &Expr, types: &Types) {
+        match (t, e) {
+            (InnerType::Scalar(s), e) => assert_assignable(*s, e, types),
+            (InnerType::Array(_, t) | InnerType::List(t), Expr::Array(l)) => {
+                for e in l.as_ref() {
+                    Self::check_init(t, e, types);
+                }
+            }
+            (InnerType::Struct(s), Expr::Array(l)) => {
+                if s.1.len() != l.len() {
+                    panic!(
+                        "struct {} has {} fields, found {} values",
+                        s.0,
+                        s.1.len(),
+                        l.len()
+                    );
+                }
+                for (TypedVariable(t, _), e) in s.1.iter().zip(l.as_ref()) {
+                    Self::check_init(&t.inner, e, types);
+                }
+            }
+            (t, e) => {
+                if e.type_of(types).is_some() || matches!(e, Expr::Const(_)) {
+                    panic!("cannot initialize {t} with `{e}`")
+                }
+            }
+        }
+    }
+
     fn check_dup(statement: &Statement) {
         match statement {
             Statement::Mut(x, _, e) | Statement::IndexedMut(x, _, _, e) if e.has_variable(x) => {
                 panic!("variable `{}` appears on the both sides", x.0)
             }
-            Statement::IndexedSwap(x, l, r) if l.has_variable(x) || r.has_variable(x) => {
+            Statement::Rotate(Place(x, is), _, e) if e.has_variable(x) || is.has_variable(x) => {
+                panic!("variable `{}` appears on the both sides", x.0)
+            }
+            Statement::IndexedSwap(Place(x, is), l, r)
+                if is.has_variable(x) || l.has_variable(x) || r.has_variable(x) =>
+            {
                 panic!("variable `{}` appears as a container and as an index", x.0)
             }
-            Statement::Call(_, xs) | Statement::Uncall(_, xs) => {
-                let mut set = BTreeSet::new();
-                for x in xs {
-                    match set.get(x) {
-                        None => {
-                            set.insert(x);
+            Statement::Reverse(x, Some((from, to)))
+                if mentions_elements(from, x) || mentions_elements(to, x) =>
+            {
+                panic!("variable `{}` appears as a container and as an index", x.0)
+            }
+            Statement::Complement(Place(x, is)) | Statement::Negate(Place(x, is))
+                if is.has_variable(x) =>
+            {
+                panic!("variable `{}` appears as a container and as an index", x.0)
+            }
+            Statement::Cswap(c, l, r) => {
+                if c.has_variable(&l.0) || c.has_variable(&r.0) {
+                    panic!("control `{c}` of cswap must be distinct from `{l}` and `{r}`")
+                }
+                for p @ Place(_, is) in [c, l, r] {
+                    if is.has_variable(&l.0) || is.has_variable(&r.0) {
+                        panic!("swapped variables of cswap appear in an index of `{p}`")
+                    }
+                }
+            }
+            Statement::Call(_, args) | Statement::Uncall(_, args) => {
+                let places: Vec<_> = args.iter().filter_map(Place::of).collect();
+                for (k, p) in places.iter().enumerate() {
+                    for q in &places[k + 1..] {
+                        if let Alias::Always = p.alias(q) {
+                            if p.1.len() == q.1.len() {
+                                panic!("variable `{q}` is passed more than once")
+                            }
+                            let (outer, inner) = if p.1.len() < q.1.len() {
+                                (p, q)
+                            } else {
+                                (q, p)
+                            };
+                            panic!("variable `{inner}` is passed along with `{outer}`")
                         }
-                        Some(_) => panic!("variable `{}` is passed more than once", x.0),
+                    }
+                }
+                for p in &places {
+                    if let Some((s, _)) = p.0.0.split_once('.')
+                        && places.iter().any(|Place(x, _)| x.0.as_str() == s)
+                    {
+                        panic!("variable `{p}` is passed along with `{s}`")
                     }
                 }
             }
             Statement::IfThenElseFi(_, s_l, s_r, _)
             | Statement::FromDoLoopUntil(_, s_l, s_r, _)
             | Statement::IfThenElse(_, s_l, s_r)
-            | Statement::Sequence(s_l, s_r) => {
+            | Statement::Sequence(s_l, s_r)
+            | Statement::InvlBlock(s_l, s_r) => {
                 Self::check_dup(s_l);
                 Self::check_dup(s_r);
             }
-            Statement::LocalDelocal(_, _, s, _, _) | Statement::For(For { statement: s, .. }) => {
-                Self::check_dup(s)
+            Statement::Case(Case { arms, default, .. }) => {
+                for (_, s) in arms {
+                    Self::check_dup(s);
+                }
+                Self::check_dup(default);
+            }
+            Statement::LocalDelocal(_, _, s, _, _)
+            | Statement::For(For { statement: s, .. })
+            | Statement::ForRange(Range { statement: s, .. })
+            | Statement::Located(_, s) => Self::check_dup(s),
+            _ => {}
+        }
+    }
+
+    /// Rejects the statements of a fn with effects beyond its result and its locals; the
+    /// parameters are `const`, so the checks of `check_type` reject any other mutation.
+    fn check_pure(q: &ProcId, statement: &Statement) {
+        match statement {
+            Statement::Call(p, _) | Statement::Uncall(p, _) => {
+                panic!("fn `{}` cannot call proc `{}`", q.0, p.0)
+            }
+            Statement::Print(x) => panic!("fn `{}` cannot print `{}`", q.0, x.0),
+            Statement::IfThenElseFi(_, s_l, s_r, _)
+            | Statement::FromDoLoopUntil(_, s_l, s_r, _)
+            | Statement::IfThenElse(_, s_l, s_r)
+            | Statement::Sequence(s_l, s_r)
+            | Statement::InvlBlock(s_l, s_r) => {
+                Self::check_pure(q, s_l);
+                Self::check_pure(q, s_r);
             }
+            Statement::Case(Case { arms, default, .. }) => {
+                for (_, s) in arms {
+                    Self::check_pure(q, s);
+                }
+                Self::check_pure(q, default);
+            }
+            Statement::LocalDelocal(_, _, s, _, _)
+            | Statement::For(For { statement: s, .. })
+            | Statement::ForRange(Range { statement: s, .. })
+            | Statement::Located(_, s) => Self::check_pure(q, s),
             _ => {}
         }
     }
 
+    fn check_function(&self, function: &Function) {
+        let Function(q, params, result, body) = function;
+        if result.0.r#const {
+            panic!("result `{}` of fn `{}` cannot be const", result.1.0, q.0);
+        }
+        Self::check_pure(q, body);
+        self.check_invl_blocks(body);
+
+        let mut types = self.scope(&LinkedList::new(), params);
+        types.extend(result.bindings());
+        body.check_type(&mut types, &self.signatures);
+        Self::check_dup(body);
+    }
+
     fn check_main(&self, main: &MainProc) {
         let MainProc(decls, statement, invl) = main;
         self.ban_inj_call(invl);
+        self.check_invl_blocks(statement);
+        self.check_invl_blocks(invl);
+
+        let mut types = self.functions.clone();
+        for (t_x, e) in decls {
+            if let Some(e) = e {
+                Self::check_init(&t_x.0.inner, e, &types);
+            }
+            types.extend(t_x.bindings());
+        }
+        statement.check_type(&mut types, &self.signatures);
+        invl.check_type(&mut types, &self.signatures);
 
         Self::check_dup(statement);
         Self::check_dup(invl);
@@ -130,16 +356,50 @@ impl Checker {
 
     fn check_proc(&self, proc: &Proc) {
         match proc {
-            Proc::Invl(_, params, statement, invl) => {
-                self.ban_inj_call(invl);
+            Proc::Invl(_, sizes, params, contract, statement, invl) => {
+                let this = self.local(params);
+                let this = &this;
+                this.ban_inj_call(invl);
+                this.check_invl_blocks(statement);
+                this.check_invl_blocks(invl);
+
+                let mut types = this.scope(sizes, params);
+                contract.check_type(&mut types, &this.signatures);
+                statement.check_type(&mut types, &this.signatures);
+                invl.check_type(&mut types, &this.signatures);
+
                 Self::check_dup(statement);
                 Self::check_dup(invl);
 
                 let mut mutables = Self::mutables(params);
                 invl.check_mut(&mut mutables);
             }
-            Proc::Inj(_, _, statement) => Self::check_dup(statement),
+            Proc::Inj(_, sizes, params, contract, statement) => {
+                let this = self.local(params);
+                this.check_invl_blocks(statement);
+                let mut types = this.scope(sizes, params);
+                contract.check_type(&mut types, &this.signatures);
+                statement.check_type(&mut types, &this.signatures);
+                Self::check_dup(statement);
+            }
             Proc::Mat(_, _) => {}
+            Proc::Conj(id, sizes, params, q, r) => {
+                if q == id || r == id {
+                    panic!("`{}` cannot be conjugated by itself", id.0);
+                }
+                if !self.signatures.contains_key(q) {
+                    panic!("undefined function found: {:?}", q.0);
+                }
+                self.assert_invl(r);
+
+                let types = self.scope(sizes, params);
+                let args = params
+                    .iter()
+                    .map(|TypedVariable(_, x)| Expr::Variable(x.clone()))
+                    .collect();
+                check_call(q, &args, &types, &self.signatures);
+                check_call(r, &args, &types, &self.signatures);
+            }
         }
     }
 }
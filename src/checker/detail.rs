@@ -1,15 +1,39 @@
-use crate::checker::Mutables;
-use crate::parser::detail::{Expr, Statement, Variable};
-use crate::parser::r#for::For;
+use crate::checker::{Mutables, Signatures, Types};
+use crate::parser::detail::{
+    BinOp, Contract, Expr, InnerType, MutOp, Place, ProcId, ProcKind, Scalar, Size, Statement,
+    Type, TypedVariable, UnrOp, Variable, VariableOrLiteral,
+};
+use crate::parser::{
+    case::Case,
+    r#for::{For, Range},
+};
+use std::{
+    collections::{BTreeMap, LinkedList},
+    rc::Rc,
+};
 
 pub trait CheckMut {
     fn check_mut(&self, mutables: &mut Mutables);
 }
 
+pub trait CheckType {
+    fn check_type(&self, types: &mut Types, signatures: &Signatures);
+}
+
+pub trait TypeOf {
+    fn type_of(&self, types: &Types) -> Option<Scalar>;
+}
+
 pub trait HasVariable {
     fn has_variable(&self, variable: &Variable) -> bool;
 }
 
+/// Whether every mention of a container `c` is an element `c[i]...` selected by the index
+/// `i` of a range loop, so that each iteration uses a different slice of `c`.
+trait SlicedBy {
+    fn sliced_by(&self, c: &Variable, i: &Variable) -> bool;
+}
+
 impl CheckMut for Expr {
     fn check_mut(&self, mutables: &mut Mutables) {
         match self {
@@ -20,15 +44,24 @@ impl CheckMut for Expr {
                     e.check_mut(mutables);
                 }
             }
-            Self::Indexed(x, i) => {
+            Self::Indexed(x, is) => {
                 x.check_mut(mutables);
-                i.check_mut(mutables);
+                is.check_mut(mutables);
             }
             Self::BinOp(l, _, r) => {
                 l.check_mut(mutables);
                 r.check_mut(mutables);
             }
-            Self::UnrOp(_, e) | Self::Wrapped(e) => e.check_mut(mutables),
+            Self::UnrOp(_, e) | Self::Wrapped(e) | Self::Cast(_, e) => e.check_mut(mutables),
+            Self::Call(_, args) => args.check_mut(mutables),
+        }
+    }
+}
+
+impl CheckMut for LinkedList<Expr> {
+    fn check_mut(&self, mutables: &mut Mutables) {
+        for e in self {
+            e.check_mut(mutables);
         }
     }
 }
@@ -43,6 +76,24 @@ impl CheckMut for Variable {
             ),
             Some(x) => *x = true,
         }
+
+        // using a whole struct also uses each of its fields
+        let prefix = format!("{}.", self.0);
+        let fields: Vec<_> = mutables
+            .keys()
+            .filter(|x| x.0.starts_with(&prefix))
+            .cloned()
+            .collect();
+        for field in fields {
+            field.check_mut(mutables);
+        }
+    }
+}
+
+impl CheckMut for Place {
+    fn check_mut(&self, mutables: &mut Mutables) {
+        self.0.check_mut(mutables);
+        self.1.check_mut(mutables);
     }
 }
 
@@ -53,17 +104,46 @@ impl CheckMut for Statement {
                 x.check_mut(mutables);
                 e.check_mut(mutables);
             }
-            Self::IndexedMut(x, l, _, r) | Self::IndexedSwap(x, l, r) => {
+            Self::IndexedMut(x, is, _, e) => {
+                x.check_mut(mutables);
+                is.check_mut(mutables);
+                e.check_mut(mutables);
+            }
+            Self::IndexedSwap(x, l, r) => {
                 x.check_mut(mutables);
                 l.check_mut(mutables);
                 r.check_mut(mutables);
             }
+            Self::Complement(x) | Self::Negate(x) => x.check_mut(mutables),
+            Self::Cswap(c, l, r) => {
+                c.check_mut(mutables);
+                if l.0 == r.0 {
+                    l.0.check_mut(mutables);
+                    l.1.check_mut(mutables);
+                    r.1.check_mut(mutables);
+                } else {
+                    l.check_mut(mutables);
+                    r.check_mut(mutables);
+                }
+            }
+            Self::Reverse(x, range) => {
+                // the bounds may use `size(x)` or `empty(x)`, which reversing does not change
+                if let Some((from, to)) = range {
+                    let used = mutables.remove(x);
+                    from.check_mut(mutables);
+                    to.check_mut(mutables);
+                    if let Some(used) = used {
+                        mutables.insert(x.clone(), used);
+                    }
+                }
+                x.check_mut(mutables);
+            }
             Self::Call(_, args) | Self::Uncall(_, args) => {
                 for arg in args {
                     arg.check_mut(mutables);
                 }
             }
-            Self::Skip | Self::Print(_) => {}
+            Self::Skip | Self::Print(_) | Self::Assert(_, _) => {}
             Self::IfThenElse(e, s_l, s_r) => {
                 e.check_mut(mutables);
                 let mut cloned = mutables.clone();
@@ -74,9 +154,11 @@ impl CheckMut for Statement {
                 }
             }
             Self::For(For {
+                index,
                 vars,
                 containers,
                 statement,
+                ..
             }) => {
                 let cloned = mutables.clone();
 
@@ -91,6 +173,9 @@ impl CheckMut for Statement {
                     *b = true;
                 }
 
+                if let Some(k) = index {
+                    mutables.remove(k);
+                }
                 for (vs, (c, _)) in vars.iter().zip(containers) {
                     if mutables.get(c).is_some() {
                         for v in vs {
@@ -103,24 +188,966 @@ impl CheckMut for Statement {
 
                 *mutables = cloned;
             }
+            Self::ForRange(Range {
+                var,
+                from,
+                to,
+                statement,
+                ..
+            }) => {
+                let cloned = mutables.clone();
+
+                from.check_mut(mutables);
+                to.check_mut(mutables);
+
+                // each iteration uses a different element `c[var]` of a sliced container `c`, but
+                // would use any other variable again
+                let sliced: Vec<_> = mutables
+                    .iter()
+                    .filter(|(c, used)| {
+                        !**used && statement.has_variable(c) && statement.sliced_by(c, var)
+                    })
+                    .map(|(c, _)| c.clone())
+                    .collect();
+                for (_, b) in mutables.iter_mut() {
+                    *b = true;
+                }
+                for c in &sliced {
+                    mutables.insert(c.clone(), false);
+                }
+                mutables.remove(var);
+
+                statement.check_mut(mutables);
+
+                *mutables = cloned;
+                for c in &sliced {
+                    c.check_mut(mutables);
+                }
+            }
             Self::Sequence(l, r) => {
                 l.check_mut(mutables);
                 r.check_mut(mutables);
             }
+            Self::InvlBlock(s, i) => {
+                let used: Vec<_> = mutables
+                    .keys()
+                    .filter(|x| s.has_variable(x) || i.has_variable(x))
+                    .cloned()
+                    .collect();
+                for x in used {
+                    x.check_mut(mutables);
+                }
+            }
+            Self::Located(_, s) => s.check_mut(mutables),
             _ => unreachable!(),
         }
     }
 }
 
+impl HasVariable for Place {
+    fn has_variable(&self, variable: &Variable) -> bool {
+        &self.0 == variable || self.1.has_variable(variable)
+    }
+}
+
+impl HasVariable for LinkedList<Expr> {
+    fn has_variable(&self, variable: &Variable) -> bool {
+        self.iter().any(|e| e.has_variable(variable))
+    }
+}
+
+impl HasVariable for VariableOrLiteral {
+    fn has_variable(&self, variable: &Variable) -> bool {
+        matches!(self, Self::Variable(x) if x == variable)
+    }
+}
+
+impl HasVariable for Statement {
+    fn has_variable(&self, variable: &Variable) -> bool {
+        let is = |x: &Variable| x == variable;
+        match self {
+            Self::Mut(x, _, e) => is(x) || e.has_variable(variable),
+            Self::IndexedMut(x, is_, _, e) => {
+                is(x) || is_.has_variable(variable) || e.has_variable(variable)
+            }
+            Self::IndexedSwap(x, l, r) => {
+                x.has_variable(variable) || l.has_variable(variable) || r.has_variable(variable)
+            }
+            Self::IfThenElseFi(e_l, s_l, s_r, e_r) | Self::FromDoLoopUntil(e_l, s_l, s_r, e_r) => {
+                e_l.has_variable(variable)
+                    || s_l.has_variable(variable)
+                    || s_r.has_variable(variable)
+                    || e_r.has_variable(variable)
+            }
+            Self::Rotate(x, _, e) => x.has_variable(variable) || e.has_variable(variable),
+            Self::Complement(x) | Self::Negate(x) => x.has_variable(variable),
+            Self::Cswap(c, l, r) => {
+                c.has_variable(variable) || l.has_variable(variable) || r.has_variable(variable)
+            }
+            Self::Reverse(x, range) => {
+                is(x)
+                    || range.as_ref().is_some_and(|(from, to)| {
+                        from.has_variable(variable) || to.has_variable(variable)
+                    })
+            }
+            Self::PushFront(l, r)
+            | Self::PushBack(l, r)
+            | Self::PopFront(l, r)
+            | Self::PopBack(l, r) => l.has_variable(variable) || is(r),
+            Self::LocalDelocal(TypedVariable(_, x), e_l, s, _, e_r) => {
+                is(x)
+                    || e_l.has_variable(variable)
+                    || s.has_variable(variable)
+                    || e_r.has_variable(variable)
+            }
+            Self::Call(_, args) | Self::Uncall(_, args) => args.has_variable(variable),
+            Self::Skip => false,
+            Self::Print(x) => is(x),
+            Self::Assert(_, e) => e.has_variable(variable),
+            Self::For(For {
+                index,
+                vars,
+                containers,
+                statement,
+                ..
+            }) => {
+                index.as_ref().is_some_and(is)
+                    || vars.iter().flatten().any(is)
+                    || containers
+                        .iter()
+                        .any(|(c, i)| is(c) || i.as_ref().is_some_and(is))
+                    || statement.has_variable(variable)
+            }
+            Self::ForRange(Range {
+                var,
+                from,
+                to,
+                statement,
+                ..
+            }) => {
+                is(var)
+                    || from.has_variable(variable)
+                    || to.has_variable(variable)
+                    || statement.has_variable(variable)
+            }
+            Self::IfThenElse(e, s_l, s_r) => {
+                e.has_variable(variable) || s_l.has_variable(variable) || s_r.has_variable(variable)
+            }
+            Self::Case(Case {
+                scrutinee,
+                arms,
+                default,
+                exit,
+            }) => {
+                scrutinee.has_variable(variable)
+                    || arms
+                        .iter()
+                        .any(|(v, s)| v.has_variable(variable) || s.has_variable(variable))
+                    || default.has_variable(variable)
+                    || exit.has_variable(variable)
+            }
+            Self::Sequence(l, r) | Self::InvlBlock(l, r) => {
+                l.has_variable(variable) || r.has_variable(variable)
+            }
+            Self::Located(_, s) => s.has_variable(variable),
+        }
+    }
+}
+
 impl HasVariable for Expr {
     fn has_variable(&self, variable: &Variable) -> bool {
         match self {
             Self::Const(_) | Self::Nil => false,
             Self::Variable(x) | Self::Empty(x) | Self::Size(x) => x == variable,
             Self::Array(l) => l.iter().any(|e| e.has_variable(variable)),
-            Self::Indexed(x, i) => x == variable || i.has_variable(variable),
+            Self::Indexed(x, is) => x == variable || is.has_variable(variable),
             Self::BinOp(l, _, r) => l.has_variable(variable) || r.has_variable(variable),
-            Self::UnrOp(_, e) | Self::Wrapped(e) => e.has_variable(variable),
+            Self::UnrOp(_, e) | Self::Wrapped(e) | Self::Cast(_, e) => e.has_variable(variable),
+            Self::Call(_, args) => args.has_variable(variable),
+        }
+    }
+}
+
+impl SlicedBy for Expr {
+    fn sliced_by(&self, c: &Variable, i: &Variable) -> bool {
+        match self {
+            Self::Const(_) | Self::Nil => true,
+            Self::Variable(x) | Self::Empty(x) | Self::Size(x) => x != c,
+            Self::Array(l) | Self::Call(_, l) => l.sliced_by(c, i),
+            Self::Indexed(x, is) => element_sliced_by(x, is, c, i),
+            Self::BinOp(l, _, r) => l.sliced_by(c, i) && r.sliced_by(c, i),
+            Self::UnrOp(_, e) | Self::Wrapped(e) | Self::Cast(_, e) => e.sliced_by(c, i),
+        }
+    }
+}
+
+impl SlicedBy for LinkedList<Expr> {
+    fn sliced_by(&self, c: &Variable, i: &Variable) -> bool {
+        self.iter().all(|e| e.sliced_by(c, i))
+    }
+}
+
+impl SlicedBy for Place {
+    fn sliced_by(&self, c: &Variable, i: &Variable) -> bool {
+        element_sliced_by(&self.0, &self.1, c, i)
+    }
+}
+
+fn element_sliced_by(x: &Variable, is: &LinkedList<Expr>, c: &Variable, i: &Variable) -> bool {
+    (x != c || matches!(is.front(), Some(Expr::Variable(k)) if k == i)) && is.sliced_by(c, i)
+}
+
+impl SlicedBy for Statement {
+    fn sliced_by(&self, c: &Variable, i: &Variable) -> bool {
+        let not = |x: &Variable| x != c;
+        match self {
+            Self::Mut(x, _, e) => not(x) && e.sliced_by(c, i),
+            Self::IndexedMut(x, is, _, e) => element_sliced_by(x, is, c, i) && e.sliced_by(c, i),
+            Self::IndexedSwap(x, l, r) => {
+                x.sliced_by(c, i) && l.sliced_by(c, i) && r.sliced_by(c, i)
+            }
+            Self::IfThenElseFi(e_l, s_l, s_r, e_r) | Self::FromDoLoopUntil(e_l, s_l, s_r, e_r) => {
+                e_l.sliced_by(c, i)
+                    && s_l.sliced_by(c, i)
+                    && s_r.sliced_by(c, i)
+                    && e_r.sliced_by(c, i)
+            }
+            Self::Rotate(x, _, e) => x.sliced_by(c, i) && e.sliced_by(c, i),
+            Self::Complement(x) | Self::Negate(x) => x.sliced_by(c, i),
+            Self::Cswap(x, l, r) => x.sliced_by(c, i) && l.sliced_by(c, i) && r.sliced_by(c, i),
+            Self::Reverse(x, range) => {
+                not(x)
+                    && range
+                        .as_ref()
+                        .is_none_or(|(from, to)| from.sliced_by(c, i) && to.sliced_by(c, i))
+            }
+            Self::PushFront(l, r)
+            | Self::PushBack(l, r)
+            | Self::PopFront(l, r)
+            | Self::PopBack(l, r) => !l.has_variable(c) && not(r),
+            Self::LocalDelocal(_, e_l, s, _, e_r) => {
+                e_l.sliced_by(c, i) && s.sliced_by(c, i) && e_r.sliced_by(c, i)
+            }
+            Self::Call(_, args) | Self::Uncall(_, args) => args.sliced_by(c, i),
+            Self::Skip => true,
+            Self::Print(x) => not(x),
+            Self::Assert(_, e) => e.sliced_by(c, i),
+            Self::For(_) | Self::ForRange(_) | Self::InvlBlock(_, _) => !self.has_variable(c),
+            Self::IfThenElse(e, s_l, s_r) => {
+                e.sliced_by(c, i) && s_l.sliced_by(c, i) && s_r.sliced_by(c, i)
+            }
+            Self::Case(Case {
+                scrutinee,
+                arms,
+                default,
+                exit,
+            }) => {
+                scrutinee.sliced_by(c, i)
+                    && arms
+                        .iter()
+                        .all(|(v, s)| v.sliced_by(c, i) && s.sliced_by(c, i))
+                    && default.sliced_by(c, i)
+                    && exit.sliced_by(c, i)
+            }
+            Self::Sequence(l, r) => l.sliced_by(c, i) && r.sliced_by(c, i),
+            Self::Located(_, s) => s.sliced_by(c, i),
+        }
+    }
+}
+
+pub fn lookup<'a>(types: &'a Types, x: &Variable) -> &'a Type {
+    types
+        .get(x)
+        .unwrap_or_else(|| panic!("undefined variable `{}`", x.0))
+}
+
+fn element<'a>(types: &'a Types, x: &Variable) -> &'a InnerType {
+    lookup(types, x)
+        .inner
+        .element()
+        .unwrap_or_else(|| panic!("`{}` is not a container", x.0))
+}
+
+/// The type of `x[i][j]...`; every index but the last must select a container.
+fn indexed_type<'a>(types: &'a Types, x: &Variable, indices: &LinkedList<Expr>) -> &'a InnerType {
+    let mut t = &lookup(types, x).inner;
+    let mut path = x.0.to_string();
+    for i in indices {
+        if !is_scalar(i, types) {
+            panic!("index `{i}` of `{path}` is not a scalar");
+        }
+        i.type_of(types);
+        t = t
+            .element()
+            .unwrap_or_else(|| panic!("`{path}` is not a container"));
+        path += &format!("[{i}]");
+    }
+    t
+}
+
+/// Whether every operand in `e` is a scalar.
+fn is_scalar(e: &Expr, types: &Types) -> bool {
+    match e {
+        Expr::Variable(x) => matches!(lookup(types, x).inner, InnerType::Scalar(_)),
+        Expr::Array(_) | Expr::Nil => false,
+        Expr::BinOp(l, _, r) => is_scalar(l, types) && is_scalar(r, types),
+        Expr::UnrOp(_, e) | Expr::Wrapped(e) | Expr::Cast(_, e) => is_scalar(e, types),
+        _ => true,
+    }
+}
+
+/// A condition must be a scalar expression, e.g. not `p = q` for structs `p` and `q`.
+fn check_condition(e: &Expr, construct: &str, types: &Types) {
+    if !is_scalar(e, types) {
+        panic!("condition `{e}` of {construct} is not a scalar");
+    }
+    e.type_of(types);
+}
+
+fn indexed_scalar(types: &Types, x: &Variable, indices: &LinkedList<Expr>) -> Scalar {
+    match indexed_type(types, x, indices) {
+        InnerType::Scalar(s) => *s,
+        _ => panic!("`{}` is not a scalar", Place(x.clone(), indices.clone())),
+    }
+}
+
+fn scalar(types: &Types, x: &Variable) -> Scalar {
+    match &lookup(types, x).inner {
+        InnerType::Scalar(s) => *s,
+        _ => panic!("`{}` is not a scalar", x.0),
+    }
+}
+
+pub fn assert_assignable(target: Scalar, e: &Expr, types: &Types) {
+    if !is_scalar(e, types) {
+        panic!("`{e}` is not a scalar");
+    }
+    match (target, e.type_of(types)) {
+        (Scalar::Fixed(_), Some(s)) if s != target => {
+            panic!("cannot implicitly convert {s} to {target} in `{e}`; use `{target}(...)`")
+        }
+        _ => {}
+    }
+}
+
+fn place_type(Place(x, is): &Place, types: &Types) -> Scalar {
+    indexed_scalar(types, x, is)
+}
+
+/// Matches an argument type against a parameter type, binding size parameters.
+fn unify(param: &InnerType, arg: &InnerType, sizes: &mut BTreeMap<Variable, Size>) -> bool {
+    match (param, arg) {
+        (InnerType::Array(Size::Param(n), t), InnerType::Array(m, u)) => {
+            sizes.entry(n.clone()).or_insert_with(|| m.clone()) == m && unify(t, u, sizes)
+        }
+        (InnerType::Array(n, t), InnerType::Array(m, u)) => n == m && unify(t, u, sizes),
+        (InnerType::List(t), InnerType::List(u)) => unify(t, u, sizes),
+        (t, u) => t == u,
+    }
+}
+
+/// Replaces the sizes bound so far by their values.
+fn subst(t: &InnerType, sizes: &BTreeMap<Variable, Size>) -> InnerType {
+    match t {
+        InnerType::Array(Size::Param(n), u) if sizes.contains_key(n) => {
+            InnerType::Array(sizes[n].clone(), Rc::new(subst(u, sizes)))
+        }
+        InnerType::Array(n, u) => InnerType::Array(n.clone(), Rc::new(subst(u, sizes))),
+        InnerType::List(u) => InnerType::List(Rc::new(subst(u, sizes))),
+        t => t.clone(),
+    }
+}
+
+/// Whether a proc of `kind` can be passed as a proc parameter of kind `expected`.
+fn subsumes(kind: ProcKind, expected: ProcKind) -> bool {
+    kind == ProcKind::Invl || expected == ProcKind::Inj
+}
+
+/// Whether a global proc with signature `(kind, params)` can be passed as `expected`.
+/// The sizes of a generic proc are inferred from `expected`.
+fn accepts(expected: &InnerType, kind: ProcKind, params: &LinkedList<Type>) -> bool {
+    let InnerType::Proc(k, ts) = expected else {
+        unreachable!()
+    };
+    let mut sizes = BTreeMap::new();
+    subsumes(kind, *k)
+        && ts.len() == params.len()
+        && ts
+            .iter()
+            .zip(params)
+            .all(|(t, u)| t.r#const == u.r#const && unify(&u.inner, &t.inner, &mut sizes))
+}
+
+/// Whether `e` mentions `x` other than in `size(x)` or `empty(x)`, which do not change when
+/// the elements of `x` are reordered.
+pub fn mentions_elements(e: &Expr, x: &Variable) -> bool {
+    match e {
+        Expr::Size(_) | Expr::Empty(_) => false,
+        Expr::Indexed(y, is) => y == x || is.iter().any(|i| mentions_elements(i, x)),
+        Expr::Array(l) | Expr::Call(_, l) => l.iter().any(|e| mentions_elements(e, x)),
+        Expr::BinOp(l, _, r) => mentions_elements(l, x) || mentions_elements(r, x),
+        Expr::UnrOp(_, e) | Expr::Wrapped(e) | Expr::Cast(_, e) => mentions_elements(e, x),
+        _ => e.has_variable(x),
+    }
+}
+
+/// Whether `e` mentions `x`, a field of `x`, or the struct `x` is a field of.
+fn mentions(e: &Expr, x: &Variable, types: &Types) -> bool {
+    let prefix = format!("{}.", x.0);
+    e.has_variable(x)
+        || types
+            .keys()
+            .any(|f| f.0.starts_with(&prefix) && e.has_variable(f))
+        || x.0
+            .split_once('.')
+            .is_some_and(|(s, _)| e.has_variable(&Variable::new(s.to_string())))
+}
+
+pub fn check_call(q: &ProcId, args: &LinkedList<Expr>, types: &Types, signatures: &Signatures) {
+    let (_, params) = signatures
+        .get(q)
+        .unwrap_or_else(|| panic!("undefined function found: {:?}", q.0));
+    if params.len() != args.len() {
+        panic!(
+            "`{}` takes {} arguments, found {}",
+            q.0,
+            params.len(),
+            args.len()
+        )
+    }
+
+    let mut sizes = BTreeMap::new();
+    let mut mutables = Vec::new();
+    let mut reads = Vec::new();
+    for (t, arg) in params.iter().zip(args) {
+        match (&t.inner, arg) {
+            (InnerType::Proc(_, _), _) => {}
+            (_, Expr::Variable(x)) => {
+                let t_x = lookup(types, x);
+                if t_x.r#const && !t.r#const {
+                    panic!("const variable `{}` is passed to `{}` as mutable", x.0, q.0)
+                }
+                if !unify(&t.inner, &t_x.inner, &mut sizes) {
+                    panic!(
+                        "cannot pass {} `{}` to `{}` as {}",
+                        t_x.inner, x.0, q.0, t.inner
+                    )
+                }
+                if !t.r#const {
+                    mutables.push(x);
+                }
+            }
+            (_, Expr::Indexed(x, is)) if !t.r#const || indexed_type(types, x, is) == &t.inner => {
+                if lookup(types, x).r#const && !t.r#const {
+                    panic!("const variable `{arg}` is passed to `{}` as mutable", q.0)
+                }
+                let t_x = indexed_type(types, x, is);
+                if !unify(&t.inner, t_x, &mut sizes) {
+                    panic!("cannot pass {t_x} `{arg}` to `{}` as {}", q.0, t.inner)
+                }
+                if !t.r#const {
+                    mutables.push(x);
+                }
+                reads.extend(is.iter());
+            }
+            (t, e @ (Expr::Array(_) | Expr::Nil)) => {
+                panic!("cannot pass `{e}` to `{}` as {t}", q.0)
+            }
+            (InnerType::Scalar(s), e) if t.r#const => {
+                assert_assignable(*s, e, types);
+                reads.push(e);
+            }
+            (InnerType::Scalar(_), e) => {
+                panic!("`{e}` cannot be passed to `{}` as mutable", q.0)
+            }
+            (t, e) => panic!("cannot pass `{e}` to `{}` as {t}", q.0),
+        }
+    }
+
+    // the inverse call would evaluate an expression or an index after the mutation of such
+    // a variable
+    for e in reads {
+        for x in &mutables {
+            if mentions(e, x, types) {
+                panic!(
+                    "`{e}` passed to `{}` mentions `{}`, which is passed as mutable",
+                    q.0, x.0
+                )
+            }
+        }
+    }
+
+    // sizes are bound by the other arguments before the procs are matched against them
+    for (t, arg) in params.iter().zip(args) {
+        if let InnerType::Proc(_, _) = t.inner {
+            let Expr::Variable(f) = arg else {
+                panic!("`{arg}` passed to `{}` is not a proc", q.0)
+            };
+            let expected = match subst(&t.inner, &sizes) {
+                InnerType::Proc(k, ts) => {
+                    let ts = ts.iter().map(|t| Type {
+                        r#const: t.r#const,
+                        inner: subst(&t.inner, &sizes),
+                    });
+                    InnerType::Proc(k, Rc::new(ts.collect()))
+                }
+                _ => unreachable!(),
+            };
+            let ok = match (types.get(f), &expected) {
+                (Some(t_f), InnerType::Proc(k, ts)) => match &t_f.inner {
+                    InnerType::Proc(kind, us) => subsumes(*kind, *k) && us == ts,
+                    t => panic!("cannot pass {t} `{}` to `{}` as {expected}", f.0, q.0),
+                },
+                (None, _) => match signatures.get(&ProcId(f.0.clone())) {
+                    Some((kind, params)) => accepts(&expected, *kind, params),
+                    None => panic!("undefined function found: {:?}", f.0),
+                },
+                _ => unreachable!(),
+            };
+            if !ok {
+                panic!("cannot pass `{}` to `{}` as {expected}", f.0, q.0)
+            }
+        }
+    }
+}
+
+/// Checks a call of a `fn` in an expression and returns the type of its result.
+fn check_fn_call(q: &ProcId, args: &LinkedList<Expr>, types: &Types) -> Scalar {
+    let (params, result) = match types.get(&Variable(q.0.clone())).map(|t| &t.inner) {
+        Some(InnerType::Fn(params, result)) => (params, *result),
+        Some(t) => panic!("cannot call {t} `{}`", q.0),
+        None => panic!("undefined fn `{}`", q.0),
+    };
+    if params.len() != args.len() {
+        panic!(
+            "`{}` takes {} arguments, found {}",
+            q.0,
+            params.len(),
+            args.len()
+        )
+    }
+
+    for (t, arg) in params.iter().zip(args) {
+        let t_arg = match arg {
+            Expr::Variable(x) => Some(&lookup(types, x).inner),
+            Expr::Indexed(x, is) => Some(indexed_type(types, x, is)),
+            _ => None,
+        };
+        match (&t.inner, t_arg) {
+            (InnerType::Scalar(s), None | Some(InnerType::Scalar(_))) => {
+                assert_assignable(*s, arg, types)
+            }
+            (t, Some(u)) if u == t => {}
+            (t, Some(u)) => panic!("cannot pass {u} `{arg}` to `{}` as {t}", q.0),
+            (t, None) => panic!("cannot pass `{arg}` to `{}` as {t}", q.0),
+        }
+    }
+    result
+}
+
+fn check_swap(l: &Place, r: &Expr, types: &Types) {
+    let r = match r {
+        Expr::Variable(y) => Place(y.clone(), LinkedList::new()),
+        Expr::Indexed(y, is) => Place(y.clone(), is.as_ref().clone()),
+        _ => unreachable!(),
+    };
+    if indexed_type(types, &l.0, &l.1) != indexed_type(types, &r.0, &r.1) {
+        panic!("cannot swap `{l}` and `{r}` of different types")
+    }
+}
+
+fn check_fixed(x: &Place, what: &str, types: &Types) {
+    if let Scalar::Int = place_type(x, types) {
+        panic!("cannot {what} `{x}` of type int; use a fixed-width type")
+    }
+}
+
+fn check_factor(x: &Variable, target: Scalar, e: &Expr, types: &Types) {
+    match e {
+        Expr::Wrapped(e) => check_factor(x, target, e, types),
+        Expr::UnrOp(UnrOp::Negative, e) if matches!(e.as_ref(), Expr::Const(_)) => {
+            check_factor(x, target, e, types)
+        }
+        Expr::Const(n) if n.is_zero() => {
+            panic!("`{}` cannot be multiplied or divided by zero", x.0)
+        }
+        Expr::Const(n) if matches!(target, Scalar::Fixed(_)) && !n.is_odd() => panic!(
+            "fixed-width `{}` can only be multiplied or divided by an odd constant",
+            x.0
+        ),
+        Expr::Const(_) => {}
+        Expr::Variable(y)
+            if lookup(types, y).r#const
+                && matches!(lookup(types, y).inner, InnerType::Scalar(_)) =>
+        {
+            assert_assignable(target, e, types)
+        }
+        _ => panic!("`{e}` is not a nonzero constant or a const variable"),
+    }
+}
+
+impl CheckType for Contract {
+    fn check_type(&self, types: &mut Types, signatures: &Signatures) {
+        self.requires.check_type(types, signatures);
+        self.ensures.check_type(types, signatures);
+    }
+}
+
+impl TypeOf for Expr {
+    fn type_of(&self, types: &Types) -> Option<Scalar> {
+        match self {
+            Self::Const(_) | Self::Nil => None,
+            Self::Variable(x) => match &lookup(types, x).inner {
+                InnerType::Scalar(s) => Some(*s),
+                _ => None,
+            },
+            Self::Empty(x) | Self::Size(x) => {
+                element(types, x);
+                None
+            }
+            Self::Array(l) => {
+                for e in l.as_ref() {
+                    e.type_of(types);
+                }
+                None
+            }
+            Self::Indexed(x, is) => Some(indexed_scalar(types, x, is)),
+            Self::BinOp(l, op, r) => {
+                let t = match (l.type_of(types), r.type_of(types)) {
+                    (Some(Scalar::Int), _) | (_, Some(Scalar::Int)) => Some(Scalar::Int),
+                    (Some(a), Some(b)) if a != b => {
+                        panic!("mismatched widths {a} and {b} in `{self}`")
+                    }
+                    (a, b) => a.or(b),
+                };
+
+                match op {
+                    BinOp::Add
+                    | BinOp::Sub
+                    | BinOp::Xor
+                    | BinOp::Mul
+                    | BinOp::Div
+                    | BinOp::Remainder
+                    | BinOp::BitwiseAnd
+                    | BinOp::BitwiseOr => t,
+                    _ => None,
+                }
+            }
+            Self::UnrOp(UnrOp::Negative, e) | Self::Wrapped(e) => e.type_of(types),
+            Self::UnrOp(UnrOp::Not, e) => {
+                e.type_of(types);
+                None
+            }
+            Self::Cast(t, e) => {
+                e.type_of(types);
+                Some(*t)
+            }
+            Self::Call(q, args) => Some(check_fn_call(q, args, types)),
+        }
+    }
+}
+
+/// The variables a statement modifies directly, not counting its sub-statements and calls.
+fn modified(statement: &Statement) -> Vec<&Variable> {
+    match statement {
+        Statement::Mut(x, MutOp::Swap, Expr::Variable(y) | Expr::Indexed(y, _))
+        | Statement::IndexedMut(x, _, MutOp::Swap, Expr::Variable(y) | Expr::Indexed(y, _))
+        | Statement::Cswap(_, Place(x, _), Place(y, _)) => vec![x, y],
+        Statement::PushFront(VariableOrLiteral::Variable(x), y)
+        | Statement::PushBack(VariableOrLiteral::Variable(x), y)
+        | Statement::PopFront(VariableOrLiteral::Variable(x), y)
+        | Statement::PopBack(VariableOrLiteral::Variable(x), y) => vec![x, y],
+        Statement::Mut(x, _, _)
+        | Statement::IndexedMut(x, _, _, _)
+        | Statement::IndexedSwap(Place(x, _), _, _)
+        | Statement::Rotate(Place(x, _), _, _)
+        | Statement::Complement(Place(x, _))
+        | Statement::Negate(Place(x, _))
+        | Statement::Reverse(x, _)
+        | Statement::PushFront(_, x)
+        | Statement::PushBack(_, x)
+        | Statement::PopFront(_, x)
+        | Statement::PopBack(_, x) => vec![x],
+        _ => vec![],
+    }
+}
+
+/// Whether `x` and `y` are the same variable, or one is a field of the other.
+fn overlaps(x: &Variable, y: &Variable) -> bool {
+    let field =
+        |x: &Variable, y: &Variable| x.0.split_once('.').is_some_and(|(s, _)| s == y.0.as_str());
+    x == y || field(x, y) || field(y, x)
+}
+
+/// Whether `statement` may modify `x`; passing `x` to a proc or looping over it counts.
+fn writes(statement: &Statement, x: &Variable) -> bool {
+    if modified(statement).into_iter().any(|y| overlaps(x, y)) {
+        return true;
+    }
+
+    match statement {
+        Statement::Call(_, args) | Statement::Uncall(_, args) => args
+            .iter()
+            .any(|arg| matches!(arg, Expr::Variable(y) | Expr::Indexed(y, _) if overlaps(x, y))),
+        Statement::For(For {
+            containers,
+            statement,
+            ..
+        }) => containers.iter().any(|(c, _)| overlaps(x, c)) || writes(statement, x),
+        Statement::IfThenElseFi(_, s_l, s_r, _)
+        | Statement::FromDoLoopUntil(_, s_l, s_r, _)
+        | Statement::IfThenElse(_, s_l, s_r)
+        | Statement::Sequence(s_l, s_r)
+        | Statement::InvlBlock(s_l, s_r) => writes(s_l, x) || writes(s_r, x),
+        Statement::Case(Case { arms, default, .. }) => {
+            arms.iter().any(|(_, s)| writes(s, x)) || writes(default, x)
+        }
+        Statement::LocalDelocal(_, _, s, _, _)
+        | Statement::ForRange(Range { statement: s, .. })
+        | Statement::Located(_, s) => writes(s, x),
+        _ => false,
+    }
+}
+
+impl CheckType for Statement {
+    fn check_type(&self, types: &mut Types, signatures: &Signatures) {
+        for x in modified(self) {
+            if lookup(types, x).r#const {
+                panic!("const variable `{}` cannot be modified", x.0)
+            }
+        }
+
+        match self {
+            Self::Mut(x, MutOp::Swap, e @ (Expr::Variable(_) | Expr::Indexed(_, _))) => {
+                check_swap(&Place(x.clone(), LinkedList::new()), e, types)
+            }
+            Self::Mut(x, MutOp::Swap, e) => assert_assignable(scalar(types, x), e, types),
+            Self::Mut(x, MutOp::Mul | MutOp::Div, e) => check_factor(x, scalar(types, x), e, types),
+            Self::Mut(x, _, e) => assert_assignable(scalar(types, x), e, types),
+            Self::IndexedMut(x, is, MutOp::Swap, e @ (Expr::Variable(_) | Expr::Indexed(_, _))) => {
+                check_swap(&Place(x.clone(), is.clone()), e, types)
+            }
+            Self::IndexedMut(x, is, MutOp::Mul | MutOp::Div, e) => {
+                check_factor(x, indexed_scalar(types, x, is), e, types);
+            }
+            Self::IndexedMut(x, is, _, e) => {
+                assert_assignable(indexed_scalar(types, x, is), e, types);
+            }
+            Self::IndexedSwap(Place(x, is), l, r) => {
+                if indexed_type(types, x, is).element().is_none() {
+                    panic!("`{}` is not a container", Place(x.clone(), is.clone()))
+                }
+                l.type_of(types);
+                r.type_of(types);
+            }
+            Self::Rotate(x, _, e) => {
+                check_fixed(x, "rotate", types);
+                e.type_of(types);
+            }
+            Self::Complement(x) => check_fixed(x, "complement", types),
+            Self::Negate(x @ Place(y, is)) => {
+                if let InnerType::Struct(_) = indexed_type(types, y, is) {
+                    panic!("cannot negate struct `{x}`")
+                }
+            }
+            Self::Cswap(c, l, r) => {
+                place_type(c, types);
+                let (s_l, s_r) = (place_type(l, types), place_type(r, types));
+                if s_l != s_r {
+                    panic!("cannot swap `{l}` and `{r}` of different types {s_l} and {s_r}")
+                }
+            }
+            Self::Reverse(x, range) => {
+                element(types, x);
+                if let Some((from, to)) = range {
+                    from.type_of(types);
+                    to.type_of(types);
+                }
+            }
+            Self::IfThenElseFi(e_l, s_l, s_r, e_r) | Self::FromDoLoopUntil(e_l, s_l, s_r, e_r) => {
+                let (l, r) = match self {
+                    Self::IfThenElseFi(_, _, _, _) => ("if", "fi"),
+                    _ => ("from", "until"),
+                };
+                check_condition(e_l, l, types);
+                s_l.check_type(types, signatures);
+                s_r.check_type(types, signatures);
+                check_condition(e_r, r, types);
+            }
+            Self::IfThenElse(e, s_l, s_r) => {
+                check_condition(e, "if", types);
+                // the inverse evaluates the condition again after the branch
+                for x in types.keys() {
+                    if e.has_variable(x) && (writes(s_l, x) || writes(s_r, x)) {
+                        panic!(
+                            "variable `{}` of condition `{e}` is modified in its branches",
+                            x.0
+                        )
+                    }
+                }
+                s_l.check_type(types, signatures);
+                s_r.check_type(types, signatures);
+            }
+            Self::Case(Case {
+                scrutinee,
+                arms,
+                default,
+                exit,
+            }) => {
+                check_condition(scrutinee, "case", types);
+                // each value is compared with the scrutinee before the arm and with the exit
+                // after it
+                for (v, _) in arms {
+                    check_condition(v, "case", types);
+                    for x in types.keys() {
+                        if v.has_variable(x) && writes(self, x) {
+                            panic!(
+                                "variable `{}` of value `{v}` of case `{scrutinee}` is modified \
+                                 in its arms",
+                                x.0
+                            )
+                        }
+                    }
+                }
+                for (_, s) in arms {
+                    s.check_type(types, signatures);
+                }
+                default.check_type(types, signatures);
+                check_condition(exit, "fi", types);
+            }
+            Self::PushFront(l, r)
+            | Self::PushBack(l, r)
+            | Self::PopFront(l, r)
+            | Self::PopBack(l, r) => {
+                let (list, t) = match &lookup(types, r).inner {
+                    list @ InnerType::List(t) => (list, t.as_ref()),
+                    _ => panic!("`{}` is not a list", r.0),
+                };
+                match l {
+                    VariableOrLiteral::Variable(x) => {
+                        let t_x = &lookup(types, x).inner;
+                        if t_x != t {
+                            panic!("cannot move {t_x} `{}` into {list} `{}`", x.0, r.0)
+                        }
+                    }
+                    VariableOrLiteral::Literal(n) => {
+                        if let InnerType::List(_) | InnerType::Array(_, _) = t {
+                            panic!("cannot move literal {n} into {list} `{}`", r.0)
+                        }
+                    }
+                }
+            }
+            Self::LocalDelocal(tx_l, e_l, s, _, e_r) => {
+                let TypedVariable(t, x) = tx_l;
+                match &t.inner {
+                    InnerType::Scalar(target) => assert_assignable(*target, e_l, types),
+                    InnerType::Struct(_) => panic!("local struct `{}` is not supported", x.0),
+                    _ => {
+                        e_l.type_of(types);
+                    }
+                }
+
+                let shadowed = types.insert(x.clone(), t.clone());
+                s.check_type(types, signatures);
+                e_r.type_of(types);
+
+                match shadowed {
+                    Some(t) => types.insert(x.clone(), t),
+                    None => types.remove(x),
+                };
+            }
+            Self::Call(q, args) | Self::Uncall(q, args) => check_call(q, args, types, signatures),
+            Self::Print(x) => {
+                lookup(types, x);
+            }
+            Self::Assert(assertion, e) => check_condition(e, &assertion.to_string(), types),
+            Self::For(For {
+                index,
+                vars,
+                containers,
+                statement,
+                ..
+            }) => {
+                // the body sees the containers only through its variables, so that it
+                // cannot change their sizes or alias an element, even through their struct
+                for (c, i) in containers {
+                    for x in [Some(c), i.as_ref()].into_iter().flatten() {
+                        if lookup(types, x).r#const {
+                            continue;
+                        }
+                        for y in types.keys().filter(|y| overlaps(x, y)) {
+                            if statement.has_variable(y) {
+                                let over = match x == y {
+                                    true => "it".to_string(),
+                                    false => format!("`{}`", x.0),
+                                };
+                                panic!("`{}` cannot be used in the body of a loop over {over}", y.0)
+                            }
+                        }
+                    }
+                }
+
+                let mut inner = types.clone();
+                if let Some(k) = index {
+                    inner.insert(k.clone(), Type::CONST_INT);
+                }
+
+                for (vs, (c, i)) in vars.iter().zip(containers) {
+                    let s = element(types, c);
+                    if let Some(i) = i {
+                        element(types, i);
+                    }
+
+                    let r#const = lookup(types, c).r#const;
+                    for v in vs {
+                        let t = Type {
+                            r#const,
+                            inner: s.clone(),
+                        };
+                        inner.insert(v.clone(), t);
+                    }
+                }
+
+                statement.check_type(&mut inner, signatures);
+            }
+            Self::ForRange(Range {
+                var,
+                from,
+                to,
+                statement,
+                ..
+            }) => {
+                // the reversed loop must count over the same range
+                for e in [from, to] {
+                    assert_assignable(Scalar::Int, e, types);
+                    for (x, t) in types.iter() {
+                        if !t.r#const && e.has_variable(x) {
+                            panic!("bound `{e}` of `{}` mentions mutable `{}`", var.0, x.0)
+                        }
+                    }
+                }
+
+                let mut inner = types.clone();
+                inner.insert(var.clone(), Type::CONST_INT);
+                statement.check_type(&mut inner, signatures);
+            }
+            Self::Sequence(l, r) => {
+                l.check_type(types, signatures);
+                r.check_type(types, signatures);
+            }
+            Self::InvlBlock(s, i) => {
+                s.check_type(types, signatures);
+                i.check_type(types, signatures);
+
+                let mut mutables = types
+                    .iter()
+                    .filter(|(_, t)| !t.r#const)
+                    .map(|(x, _)| (x.clone(), false))
+                    .collect();
+                i.check_mut(&mut mutables);
+            }
+            Self::Located(_, s) => s.check_type(types, signatures),
+            Self::Skip => {}
         }
     }
 }
@@ -1,27 +1,289 @@
 mod detail;
+mod prelude;
+mod source_map;
 
 use crate::parser::{
+    case::Case,
     detail::{
-        BinOp, Expr, InnerType, MainProc, MutOp, Proc, ProcId, Program, Statement, Type,
-        TypedVariable, UnrOp, Variable, VariableOrLiteral,
+        Alias, BinOp, Contract, Expr, Fixed, Function, InnerType, MainProc, MutOp, Place, Proc,
+        ProcId, Program, Rotation, Scalar, Statement, Struct, Type, TypedVariable, UnrOp, Variable,
+        VariableOrLiteral,
     },
-    r#for::For,
+    r#for::{For, Range},
+    literal::Literal,
+};
+use crate::tokenizer::detail::Location;
+use detail::{Flip, concat, operands, quote};
+use prelude::{Usage, Uses};
+pub use source_map::source_map;
+use std::{
+    collections::{BTreeMap, LinkedList},
+    mem,
+    rc::Rc,
 };
-use detail::{Flip, concat};
-use std::mem;
 
 const INDENT_WIDTH: usize = 4;
 
+type Signatures = BTreeMap<ProcId, LinkedList<Type>>;
+
+type Types = BTreeMap<Variable, Type>;
+
 fn indent(depth: usize) -> String {
     (0..INDENT_WIDTH * depth).map(|_| ' ').collect()
 }
 
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum IndexPolicy {
+    #[default]
+    Unchecked,
+    Checked,
+}
+
+#[derive(Debug, Default)]
+pub struct Options {
+    pub source: String,
+    pub output: String,
+    pub inline_prelude: bool,
+    pub source_map: bool,
+    pub index: IndexPolicy,
+    pub unchecked: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Ctx<'a> {
+    depth: usize,
+    options: &'a Options,
+    proc: &'a str,
+    direction: &'static str,
+    location: Location,
+    types: &'a Types,
+    sizes: &'a LinkedList<Variable>,
+    signatures: &'a Signatures,
+}
+
+impl<'a> Ctx<'a> {
+    fn new(
+        options: &'a Options,
+        proc: &'a str,
+        direction: &'static str,
+        types: &'a Types,
+        sizes: &'a LinkedList<Variable>,
+        signatures: &'a Signatures,
+    ) -> Self {
+        Self {
+            depth: 1,
+            options,
+            proc,
+            direction,
+            location: Location::default(),
+            types,
+            sizes,
+            signatures,
+        }
+    }
+
+    fn type_of(&self, x: &Variable) -> &'a Type {
+        self.types.get(x).expect("checked")
+    }
+
+    /// The parameter types of a global proc or of a proc parameter `q`.
+    fn params(&self, q: &ProcId) -> &'a LinkedList<Type> {
+        match self.types.get(&Variable(q.0.clone())) {
+            Some(Type {
+                inner: InnerType::Proc(_, ts),
+                ..
+            }) => ts,
+            _ => &self.signatures[q],
+        }
+    }
+
+    fn scalar(&self, x: &Variable) -> Scalar {
+        match self.type_of(x).inner {
+            InnerType::Scalar(s) => s,
+            _ => Scalar::Int,
+        }
+    }
+
+    fn element(&self, x: &Variable) -> &'a InnerType {
+        self.type_of(x).inner.element().expect("checked")
+    }
+
+    fn indexed(&self, x: &Variable, indices: &LinkedList<Expr>) -> Scalar {
+        let mut t = &self.type_of(x).inner;
+        for _ in indices {
+            t = t.element().expect("checked");
+        }
+        match t {
+            InnerType::Scalar(s) => *s,
+            _ => Scalar::Int,
+        }
+    }
+
+    /// The type the checker gives `e`, or `None` for an untyped literal or a non-scalar.
+    fn scalar_of(&self, e: &Expr) -> Option<Scalar> {
+        match e {
+            Expr::Variable(x) if self.sizes.contains(x) => Some(Scalar::Int),
+            Expr::Variable(x) => match self.type_of(x).inner {
+                InnerType::Scalar(s) => Some(s),
+                _ => None,
+            },
+            Expr::Indexed(x, is) => Some(self.indexed(x, is)),
+            Expr::BinOp(
+                l,
+                BinOp::Add
+                | BinOp::Sub
+                | BinOp::Xor
+                | BinOp::Mul
+                | BinOp::Div
+                | BinOp::Remainder
+                | BinOp::BitwiseAnd
+                | BinOp::BitwiseOr,
+                r,
+            ) => match (self.scalar_of(l), self.scalar_of(r)) {
+                (Some(Scalar::Int), _) | (_, Some(Scalar::Int)) => Some(Scalar::Int),
+                (a, b) => a.or(b),
+            },
+            Expr::UnrOp(UnrOp::Negative, e) | Expr::Wrapped(e) => self.scalar_of(e),
+            Expr::Cast(t, _) => Some(*t),
+            _ => None,
+        }
+    }
+
+    /// `0` for a scalar, or an empty list or zeroed array for a container.
+    fn zero(&self, x: &Variable) -> String {
+        match &self.type_of(x).inner {
+            InnerType::Scalar(_) => "0".to_string(),
+            t => format!("{}{{}}", t.cvt()),
+        }
+    }
+
+    fn init(&self, t: &Type, e: &Expr) -> String {
+        self.init_inner(&t.inner, e)
+    }
+
+    fn init_inner(&self, t: &InnerType, e: &Expr) -> String {
+        match (t, e) {
+            (InnerType::Scalar(Scalar::Fixed(f)), e) => {
+                format!("wrap<{}>({})", f.cvt(), e.cvt_in(*self))
+            }
+            (t, Expr::Nil) => format!("{}{{}}", t.cvt()),
+            // std::array needs an extra pair of braces around nested initializers
+            (InnerType::Array(_, u), Expr::Array(l)) if u.element().is_some() => {
+                format!(
+                    "{{{{{}}}}}",
+                    concat(l.as_ref(), ", ", |e| self.init_inner(u, e))
+                )
+            }
+            (InnerType::List(u), Expr::Array(l)) | (InnerType::Array(_, u), Expr::Array(l))
+                if u.element().is_some() || matches!(**u, InnerType::Scalar(Scalar::Fixed(_))) =>
+            {
+                format!(
+                    "{{{}}}",
+                    concat(l.as_ref(), ", ", |e| self.init_inner(u, e))
+                )
+            }
+            (InnerType::Struct(s), Expr::Array(l)) => format!(
+                "{{{}}}",
+                concat(
+                    s.1.iter().zip(l.as_ref()),
+                    ", ",
+                    |(TypedVariable(t, _), e)| { self.init_inner(&t.inner, e) }
+                )
+            ),
+            _ => e.cvt_in(*self),
+        }
+    }
+
+    /// Like `init`, but a literal `[...]` or `nil` names its type so that it can be compared.
+    fn value(&self, t: &Type, e: &Expr) -> String {
+        match e {
+            Expr::Array(_) => format!("{}{}", t.inner.cvt(), self.init(t, e)),
+            _ => self.init(t, e),
+        }
+    }
+
+    const fn deeper(self) -> Self {
+        Self {
+            depth: self.depth + 1,
+            ..self
+        }
+    }
+
+    /// A reversibility assertion, omitted by `--unchecked`.
+    fn expect(&self, spaces: &str, e: &Expr, expected: bool, what: &str) -> String {
+        if self.options.unchecked {
+            return String::new();
+        }
+
+        self.check(spaces, e, expected, what)
+    }
+
+    /// A type-safety assertion, kept even by `--unchecked`.
+    fn check(&self, spaces: &str, e: &Expr, expected: bool, what: &str) -> String {
+        let mut buf = Vec::new();
+        operands(e, &mut buf);
+        format!(
+            "{spaces}expect({}, {expected}, \"{what}\", {}, {}{});\n",
+            e.cvt_in(*self),
+            quote(&e.to_string()),
+            self.site(),
+            concat(&buf, "", |x| format!(
+                ", {}, {}",
+                quote(&x.to_string()),
+                x.cvt_in(*self)
+            ))
+        )
+    }
+
+    fn expect_eq(
+        &self,
+        spaces: &str,
+        actual: &str,
+        expected: &str,
+        what: &str,
+        text: &str,
+    ) -> String {
+        if self.options.unchecked {
+            return String::new();
+        }
+
+        format!(
+            "{spaces}expect_eq({actual}, {expected}, {}, {}, {});\n",
+            quote(what),
+            quote(text),
+            self.site()
+        )
+    }
+
+    fn site(&self) -> String {
+        format!(
+            "{{{}, {}, {}, {}}}",
+            quote(self.proc),
+            quote(self.direction),
+            quote(&self.options.source),
+            self.location.line
+        )
+    }
+}
+
 pub trait Cvt {
     fn cvt(&self) -> String;
 }
 
+pub trait CvtWith {
+    fn cvt_with(&self, options: &Options) -> String;
+}
+
+trait CvtProc {
+    fn cvt_proc(&self, options: &Options, signatures: &Signatures) -> String;
+}
+
 trait CvtInd {
-    fn cvt_ind(&self, depth: usize) -> String;
+    fn cvt_ind(&self, ctx: Ctx) -> String;
+}
+
+trait CvtIn {
+    fn cvt_in(&self, ctx: Ctx) -> String;
 }
 
 trait CvtRef {
@@ -33,16 +295,53 @@ trait CvtSig {
 }
 
 trait CvtMutOp {
-    fn cvt_mut_op(&self, l: &str, r: &str) -> String;
+    fn cvt_mut_op(&self, l: &str, r: &str, t: Scalar, ctx: Ctx) -> String;
 }
 
 impl CvtMutOp for MutOp {
-    fn cvt_mut_op(&self, l: &str, r: &str) -> String {
+    fn cvt_mut_op(&self, l: &str, r: &str, t: Scalar, ctx: Ctx) -> String {
+        match (self, t) {
+            (Self::Mul, _) if !ctx.options.unchecked => {
+                format!("mul_exact({l}, {r}, {});", ctx.site())
+            }
+            (Self::Div, _) if !ctx.options.unchecked => {
+                format!("div_exact({l}, {r}, {});", ctx.site())
+            }
+            (Self::Mul, Scalar::Int) => format!("{l} *= {r};"),
+            (Self::Div, Scalar::Int) => format!("{l} /= {r};"),
+            (Self::Mul, Scalar::Fixed(_)) => format!("mul_wrapping({l}, {r});"),
+            (Self::Div, Scalar::Fixed(_)) => format!("div_wrapping({l}, {r});"),
+            (Self::Add, Scalar::Int) => format!("{l} += {r};"),
+            (Self::Sub, Scalar::Int) => format!("{l} -= {r};"),
+            (Self::Xor, Scalar::Int) => format!("{l} ^= {r};"),
+            (Self::Add, Scalar::Fixed(_)) => format!("add_wrapping({l}, {r});"),
+            (Self::Sub, Scalar::Fixed(_)) => format!("sub_wrapping({l}, {r});"),
+            (Self::Xor, Scalar::Fixed(_)) => format!("xor_wrapping({l}, {r});"),
+            (Self::Swap, _) => format!("swap({l}, {r});"),
+        }
+    }
+}
+
+impl Cvt for Literal {
+    fn cvt(&self) -> String {
+        match self.to_i32() {
+            Some(x) => x.to_string(),
+            None => format!("Int(\"{self}\")"),
+        }
+    }
+}
+
+impl Cvt for Fixed {
+    fn cvt(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Cvt for Scalar {
+    fn cvt(&self) -> String {
         match self {
-            Self::Add => format!("{l} += {r};"),
-            Self::Sub => format!("{l} -= {r};"),
-            Self::Xor => format!("{l} ^= {r};"),
-            Self::Swap => format!("swap({l}, {r});"),
+            Self::Int => "Int".to_string(),
+            Self::Fixed(x) => x.cvt(),
         }
     }
 }
@@ -50,12 +349,54 @@ impl CvtMutOp for MutOp {
 impl Cvt for Type {
     fn cvt(&self) -> String {
         let r#const = if self.r#const { "const " } else { "" };
-        let inner = match self.inner {
-            InnerType::Int => "Int".to_string(),
-            InnerType::Array(n) => format!("Array<{n}>"),
-            InnerType::List => "List".to_string(),
-        };
-        format!("{}{inner}", r#const)
+        format!("{}{}", r#const, self.inner.cvt())
+    }
+}
+
+impl Cvt for InnerType {
+    fn cvt(&self) -> String {
+        let int = InnerType::Scalar(Scalar::Int);
+        match self {
+            Self::Scalar(s) => s.cvt(),
+            Self::Array(n, t) if **t == int => format!("Array<{n}>"),
+            Self::Array(n, t) => format!("Array<{n}, {}>", t.cvt()),
+            Self::List(t) if **t == int => "List".to_string(),
+            Self::List(t) => format!("ListOf<{}>", t.cvt()),
+            Self::Struct(s) => s.0.to_string(),
+            Self::Proc(_, ts) => format!(
+                "Proc<{}>",
+                concat(ts.as_ref(), ", ", |t| format!("{}&", t.cvt()))
+            ),
+            Self::Fn(_, _) => unreachable!(),
+        }
+    }
+}
+
+impl Cvt for Struct {
+    fn cvt(&self) -> String {
+        let Self(name, fields) = self;
+        let spaces = indent(1);
+        let mut buf = format!("struct {name} {{\n");
+        for field in fields {
+            buf += &format!("{spaces}{};\n", field.cvt());
+        }
+        buf += "};\n\n";
+        buf += &format!(
+            "template <> struct S<{name}> {{\n{spaces}static constexpr const char* name() noexcept {{ return \"{name}\"; }}\n}};\n\n"
+        );
+        buf += &format!(
+            "std::ostream& operator<<(std::ostream& os, const {name}& s) {{\n{spaces}return os << '{{'"
+        );
+        let mut delim = "";
+        for TypedVariable(_, x) in fields {
+            buf += &format!(
+                " << \"{1}{0}: \" << show(s.{0})",
+                x.0,
+                mem::replace(&mut delim, ", ")
+            );
+        }
+        buf += " << '}';\n}\n";
+        buf
     }
 }
 
@@ -105,25 +446,130 @@ impl Cvt for ProcId {
     }
 }
 
-impl Cvt for Program {
-    fn cvt(&self) -> String {
-        let Self(main, procs) = self;
-        let mut buf = "#include \"prelude.hpp\"\n\n".to_string();
+impl CvtWith for Program {
+    fn cvt_with(&self, options: &Options) -> String {
+        let Self(main, procs, structs, functions) = self;
+        // the prelude omits the assertions of its own procs too
+        let mut buf = match options.unchecked {
+            true => "#define INVL_UNCHECKED\n".to_string(),
+            false => String::new(),
+        };
+        buf += &if options.inline_prelude {
+            let mut usage = Usage::new(options);
+            self.uses(&mut usage);
+            prelude::inline(&usage) + "\n"
+        } else {
+            "#include \"prelude.hpp\"\n\n".to_string()
+        };
 
+        for s in structs {
+            buf += &format!("{}\n", s.cvt());
+        }
+
+        for function in functions {
+            buf += &format!("{}\n", function.cvt_sig());
+        }
         for proc in procs {
             buf += &format!("{}\n", proc.cvt_sig());
         }
 
-        buf += &format!("\n{}", main.cvt());
+        let mut signatures: Signatures = procs
+            .iter()
+            .map(|proc| (proc.id().clone(), proc.signature()))
+            .collect();
+        for Function(q, params, _, _) in functions {
+            let params = params.iter().map(|TypedVariable(t, _)| t.clone()).collect();
+            signatures.insert(q.clone(), params);
+        }
+        buf += &format!(
+            "\n{}",
+            prune_directives(&main.cvt_proc(options, &signatures))
+        );
+        buf += &reset_line(&buf, options);
 
         for proc in procs {
-            buf += &format!("\n{}", proc.cvt());
+            buf += &format!(
+                "\n{}",
+                prune_directives(&proc.cvt_proc(options, &signatures))
+            );
+            buf += &reset_line(&buf, options);
+        }
+
+        for function in functions {
+            buf += &format!(
+                "\n{}",
+                prune_directives(&function.cvt_proc(options, &signatures))
+            );
+            buf += &reset_line(&buf, options);
         }
 
         buf
     }
 }
 
+fn param_types<'a, I>(params: I) -> Types
+where
+    I: IntoIterator<Item = &'a TypedVariable>,
+{
+    params
+        .into_iter()
+        .flat_map(TypedVariable::bindings)
+        .collect()
+}
+
+fn template(sizes: &LinkedList<Variable>) -> String {
+    if sizes.is_empty() {
+        return String::new();
+    }
+    format!(
+        "template <{}>\n",
+        concat(sizes, ", ", |n| format!("std::size_t {}", n.cvt()))
+    )
+}
+
+/// Drops the `#line` directives that the compiler already counts to, and those that repeat the
+/// directive of the line before, so that a statement spanning several lines gets one directive.
+/// With `--source-map`, a directive for another column is not a repeat.
+fn prune_directives(cpp: &str) -> String {
+    let mut buf = String::new();
+    // the line the compiler counts for the next line, and the file and column in effect
+    let mut counted: Option<(usize, &str)> = None;
+    // the directives before the previous line and before this one
+    let (mut previous, mut pending) = (None, None);
+    for line in cpp.lines() {
+        let trimmed = line.trim_start();
+        if let Some((n, rest)) = trimmed
+            .strip_prefix("#line ")
+            .and_then(|d| d.split_once(' '))
+        {
+            let n = n.parse().expect("line number");
+            pending = Some(trimmed);
+            if previous != pending && counted != Some((n, rest)) {
+                counted = Some((n, rest));
+                buf += line;
+                buf += "\n";
+            }
+            continue;
+        }
+
+        previous = pending.take();
+        if let Some((n, _)) = &mut counted {
+            *n += 1;
+        }
+        buf += line;
+        buf += "\n";
+    }
+    buf
+}
+
+fn reset_line(buf: &str, options: &Options) -> String {
+    format!(
+        "#line {} {}\n",
+        buf.lines().count() + 2,
+        quote(&options.output)
+    )
+}
+
 impl Cvt for TypedVariable {
     fn cvt(&self) -> String {
         let Self(t, x) = self;
@@ -138,23 +584,28 @@ impl CvtRef for TypedVariable {
     }
 }
 
-impl Cvt for MainProc {
-    fn cvt(&self) -> String {
+impl CvtProc for MainProc {
+    fn cvt_proc(&self, options: &Options, signatures: &Signatures) -> String {
         let Self(decls, statement, invl) = self;
+        let types = param_types(decls.iter().map(|(t_x, _)| t_x));
+        let sizes = LinkedList::new();
+        let ctx = Ctx::new(options, "main", "fwd", &types, &sizes, signatures);
         let mut buf = "int main() {\n".to_string();
         let spaces = indent(1);
         for (t_x, e) in decls {
             let rhs = e
                 .as_ref()
-                .map(|x| format!(" = {}", x.cvt()))
+                .map(|x| format!(" = {}", ctx.init(&t_x.0, x)))
                 .unwrap_or("{}".to_string());
             buf += &format!("{spaces}{}{};\n", t_x.cvt(), rhs);
         }
         buf += &format!(
             "\n{}\n{}\n{}\n",
-            statement.cvt_ind(1),
-            invl.cvt_ind(1),
-            statement.flip().cvt_ind(1)
+            statement.cvt_ind(ctx),
+            invl.cvt_ind(ctx),
+            statement
+                .flip()
+                .cvt_ind(Ctx::new(options, "main", "rev", &types, &sizes, signatures))
         );
         for (TypedVariable(_, var), _) in decls {
             buf += &format!("{spaces}print(\"{0}\", {0});\n", var.0);
@@ -164,36 +615,95 @@ impl Cvt for MainProc {
     }
 }
 
-impl Cvt for Proc {
-    fn cvt(&self) -> String {
+impl CvtSig for Function {
+    fn cvt_sig(&self) -> String {
+        let Self(q, params, TypedVariable(t, _), _) = self;
+        format!(
+            "{} {}({});",
+            t.inner.cvt(),
+            q.cvt(),
+            concat(params, ", ", |param| param.cvt_ref())
+        )
+    }
+}
+
+impl CvtProc for Function {
+    fn cvt_proc(&self, options: &Options, signatures: &Signatures) -> String {
+        let Self(q, params, result, body) = self;
+        let types = param_types(params.iter().chain([result]));
+        let sizes = LinkedList::new();
+        let ctx = Ctx::new(options, &q.0, "fwd", &types, &sizes, signatures);
+        let spaces = indent(1);
+
+        let mut buf = format!("{} {{\n", self.cvt_sig().trim_end_matches(';'));
+        buf += &format!("{spaces}{}{{}};\n", result.cvt());
+        buf += &body.cvt_ind(ctx);
+        buf += &format!("{spaces}return {};\n}}\n", result.1.cvt());
+        buf
+    }
+}
+
+impl CvtProc for Proc {
+    fn cvt_proc(&self, options: &Options, signatures: &Signatures) -> String {
         let mut buf = String::new();
         let spaces = indent(1);
 
         match self {
-            Self::Inj(name, args, statement) => {
-                buf += &format!("void {}_fwd(", name.cvt());
+            Self::Inj(name, sizes, args, Contract { requires, ensures }, statement) => {
+                let types = param_types(args);
+                let fwd = Ctx::new(options, &name.0, "fwd", &types, sizes, signatures);
+                let rev = Ctx::new(options, &name.0, "rev", &types, sizes, signatures);
+                buf += &format!("{}void {}_fwd(", template(sizes), name.cvt());
                 buf += &concat(args, ", ", |arg| arg.cvt_ref());
                 buf += &format!(
-                    ") {{\n{}}}\n\nvoid {}_rev(",
-                    statement.cvt_ind(1),
+                    ") {{\n{}{}{}}}\n\n{}void {}_rev(",
+                    requires.cvt_ind(fwd),
+                    statement.cvt_ind(fwd),
+                    ensures.cvt_ind(fwd),
+                    template(sizes),
                     name.cvt()
                 );
                 buf += &concat(args, ", ", |arg| arg.cvt_ref());
-                buf += &format!(") {{\n{}}}\n", statement.flip().cvt_ind(1));
+                buf += &format!(
+                    ") {{\n{}{}{}}}\n",
+                    ensures.cvt_ind(rev),
+                    statement.flip().cvt_ind(rev),
+                    requires.cvt_ind(rev)
+                );
             }
-            Self::Invl(name, args, statement, invl) => {
-                let body = concat(args, ", ", |arg| arg.cvt_ref())
-                    + &format!(
-                        ") {{\n{}\n{}\n{}}}\n",
-                        statement.cvt_ind(1),
-                        invl.cvt_ind(1),
-                        statement.flip().cvt_ind(1)
-                    );
+            Self::Invl(name, sizes, args, Contract { requires, ensures }, statement, invl) => {
+                let types = param_types(args);
+                let body = |direction, entry: &Statement, exit: &Statement| {
+                    let ctx = Ctx::new(options, &name.0, direction, &types, sizes, signatures);
+                    concat(args, ", ", |arg| arg.cvt_ref())
+                        + &format!(
+                            ") {{\n{}{}\n{}\n{}{}}}\n",
+                            entry.cvt_ind(ctx),
+                            statement.cvt_ind(ctx),
+                            invl.cvt_ind(ctx),
+                            statement.flip().cvt_ind(ctx),
+                            exit.cvt_ind(ctx)
+                        )
+                };
 
-                buf += &format!("void {}_fwd(", name.cvt());
-                buf += &body;
-                buf += &format!("\nvoid {}_rev(", name.cvt());
-                buf += &body;
+                buf += &format!("{}void {}_fwd(", template(sizes), name.cvt());
+                buf += &body("fwd", requires, ensures);
+                buf += &format!("\n{}void {}_rev(", template(sizes), name.cvt());
+                buf += &body("rev", ensures, requires);
+            }
+            Self::Conj(name, sizes, args, q, r) => {
+                let xs = concat(args, ", ", |TypedVariable(_, x)| x.cvt());
+                buf += &format!("{}void {}_fwd(", template(sizes), name.cvt());
+                buf += &concat(args, ", ", |arg| arg.cvt_ref());
+                buf += &format!(
+                    ") {{\n{spaces}{0}_fwd({2});\n{spaces}{1}_fwd({2});\n{spaces}{0}_rev({2});\n}}\n",
+                    q.cvt(),
+                    r.cvt(),
+                    xs
+                );
+                buf += &format!("\n{}void {}_rev(", template(sizes), name.cvt());
+                buf += &concat(args, ", ", |arg| arg.cvt_ref());
+                buf += &format!(") {{\n{spaces}{}_fwd({xs});\n}}\n", name.cvt());
             }
             Self::Mat(name, mat) => {
                 let args: Vec<_> = (0..mat.size)
@@ -241,9 +751,9 @@ impl Cvt for Proc {
 impl CvtSig for Proc {
     fn cvt_sig(&self) -> String {
         let hello = match self {
-            Self::Inj(_, args, _) | Self::Invl(_, args, _, _) => {
-                concat(args, ", ", |arg| arg.cvt_ref())
-            }
+            Self::Inj(_, _, args, _, _)
+            | Self::Invl(_, _, args, _, _, _)
+            | Self::Conj(_, _, args, _, _) => concat(args, ", ", |arg| arg.cvt_ref()),
             Self::Mat(_, mat) => {
                 let args: Vec<_> = (0..mat.size).map(|i| (i, mat.nop(i))).collect();
 
@@ -253,10 +763,15 @@ impl CvtSig for Proc {
             }
         };
 
-        let (Self::Inj(name, _, _) | Self::Invl(name, _, _, _) | Self::Mat(name, _)) = self;
-        let mut buf = format!("void {}_fwd(", name.cvt());
+        let (name, template) = match self {
+            Self::Inj(name, sizes, _, _, _)
+            | Self::Invl(name, sizes, _, _, _, _)
+            | Self::Conj(name, sizes, _, _, _) => (name, template(sizes)),
+            Self::Mat(name, _) => (name, String::new()),
+        };
+        let mut buf = format!("{template}void {}_fwd(", name.cvt());
         buf += &hello;
-        buf += &format!(");\nvoid {}_rev(", name.cvt());
+        buf += &format!(");\n{template}void {}_rev(", name.cvt());
         buf += &hello;
         buf += ");";
         buf
@@ -264,136 +779,378 @@ impl CvtSig for Proc {
 }
 
 impl CvtInd for Statement {
-    fn cvt_ind(&self, depth: usize) -> String {
-        let spaces = indent(depth);
-        let more_spaces = indent(depth + 1);
+    fn cvt_ind(&self, ctx: Ctx) -> String {
+        let spaces = indent(ctx.depth);
+        let more_spaces = indent(ctx.depth + 1);
 
         match self {
-            Self::Mut(x, op, e) => spaces + &op.cvt_mut_op(&x.cvt(), &e.cvt()) + "\n",
-            Self::IndexedMut(x, i, op, e) => {
+            Self::Mut(x, op, e) => {
+                spaces + &op.cvt_mut_op(&x.cvt(), &e.cvt_in(ctx), ctx.scalar(x), ctx) + "\n"
+            }
+            Self::IndexedMut(x, is, op, e) => {
                 spaces
-                    + &op.cvt_mut_op(&format!("index({}, {})", x.cvt(), i.cvt()), &e.cvt())
+                    + &op.cvt_mut_op(
+                        &cvt_index(x, is, ctx),
+                        &e.cvt_in(ctx),
+                        ctx.indexed(x, is),
+                        ctx,
+                    )
                     + "\n"
             }
-            Self::IfThenElseFi(e_l, s_l, s_r, e_r) => format!(
-                "{spaces}if ({0}) {{\n{1}{more_spaces}assert({3});\n{spaces}}} else {{\n{2}{more_spaces}assert(!({3}));\n{spaces}}}\n",
-                e_l.cvt(),
-                s_l.cvt_ind(depth + 1),
-                s_r.cvt_ind(depth + 1),
-                e_r.cvt()
-            ),
+            Self::IfThenElseFi(e_l, s_l, s_r, e_r) => {
+                let then = s_l.cvt_ind(ctx.deeper()) + &ctx.expect(&more_spaces, e_r, true, "fi");
+                let r#else =
+                    s_r.cvt_ind(ctx.deeper()) + &ctx.expect(&more_spaces, e_r, false, "fi");
+
+                if r#else.is_empty() {
+                    format!("{spaces}if ({}) {{\n{then}{spaces}}}\n", e_l.cvt_in(ctx))
+                } else {
+                    format!(
+                        "{spaces}if ({}) {{\n{then}{spaces}}} else {{\n{}{spaces}}}\n",
+                        e_l.cvt_in(ctx),
+                        r#else
+                    )
+                }
+            }
+            Self::Case(Case {
+                scrutinee,
+                arms,
+                default,
+                exit,
+            }) => {
+                let mut buf = spaces.clone();
+                for (v, s) in arms {
+                    buf += &format!("if ({}) {{\n", equals(scrutinee, v).cvt_in(ctx));
+                    buf += &s.cvt_ind(ctx.deeper());
+                    buf += &ctx.expect(&more_spaces, &equals(exit, v), true, "case");
+                    buf += &format!("{spaces}}} else ");
+                }
+                buf += "{\n";
+                buf += &default.cvt_ind(ctx.deeper());
+                for (v, _) in arms {
+                    buf += &ctx.expect(&more_spaces, &equals(exit, v), false, "case");
+                }
+                buf + &spaces + "}\n"
+            }
             Self::FromDoLoopUntil(e_l, s_l, s_r, e_r) => format!(
-                "{spaces}assert({0});\n{1}{spaces}while (!({3})) {{\n{2}{more_spaces}assert(!({0}));\n{4}{spaces}}}\n",
-                e_l.cvt(),
-                s_l.cvt_ind(depth),
-                s_r.cvt_ind(depth + 1),
-                e_r.cvt(),
-                s_l.cvt_ind(depth + 1),
+                "{}{}{spaces}while (!({})) {{\n{}{}{}{spaces}}}\n",
+                ctx.expect(&spaces, e_l, true, "from"),
+                s_l.cvt_ind(ctx),
+                e_r.cvt_in(ctx),
+                s_r.cvt_ind(ctx.deeper()),
+                ctx.expect(&more_spaces, e_l, false, "from"),
+                s_l.cvt_ind(ctx.deeper()),
             ),
             Self::PushFront(l, r) => match l {
-                VariableOrLiteral::Literal(n) => format!("{spaces}{}.push_front({n});\n", r.cvt()),
-                VariableOrLiteral::Variable(x) => format!(
-                    "{spaces}{0}.push_front({1});\n{spaces}{1} = 0;\n",
+                VariableOrLiteral::Literal(n) => format!(
+                    "{spaces}{}.push_front({});\n",
                     r.cvt(),
-                    x.cvt()
+                    ctx.init_inner(ctx.element(r), &Expr::Const(n.clone()))
                 ),
-            },
-            Self::PushBack(l, r) => match l {
-                VariableOrLiteral::Literal(n) => format!("{spaces}{}.push_back({n});\n", r.cvt()),
                 VariableOrLiteral::Variable(x) => format!(
-                    "{spaces}{0}.push_back({1});\n{spaces}{1} = 0;\n",
+                    "{spaces}{0}.push_front({1});\n{spaces}{1} = {2};\n",
                     r.cvt(),
-                    x.cvt()
+                    x.cvt(),
+                    ctx.zero(x)
                 ),
             },
-            Self::PopFront(l, r) => match l {
+            Self::PushBack(l, r) => match l {
                 VariableOrLiteral::Literal(n) => format!(
-                    "{spaces}assert({1} == {0}.front());\n{spaces}{0}.pop_front();\n",
+                    "{spaces}{}.push_back({});\n",
                     r.cvt(),
-                    n
+                    ctx.init_inner(ctx.element(r), &Expr::Const(n.clone()))
                 ),
                 VariableOrLiteral::Variable(x) => format!(
-                    "{spaces}assert({1} == 0);\n{spaces}{1} = {0}.front();\n{spaces}{0}.pop_front();\n",
+                    "{spaces}{0}.push_back({1});\n{spaces}{1} = {2};\n",
                     r.cvt(),
-                    x.cvt()
+                    x.cvt(),
+                    ctx.zero(x)
                 ),
             },
-            Self::PopBack(l, r) => match l {
-                VariableOrLiteral::Literal(n) => format!(
-                    "{spaces}assert({1} == {0}.back());\n{spaces}{0}.pop_back();\n",
-                    r.cvt(),
-                    n
+            either @ (Self::PopFront(l, r) | Self::PopBack(l, r)) => {
+                let end = match either {
+                    Self::PopFront(_, _) => "front",
+                    Self::PopBack(_, _) => "back",
+                    _ => unreachable!(),
+                };
+
+                match l {
+                    VariableOrLiteral::Literal(n) => format!(
+                        "{}{spaces}{}.pop_{end}();\n",
+                        ctx.expect_eq(
+                            &spaces,
+                            &format!("{}.{end}()", r.cvt()),
+                            &ctx.init_inner(ctx.element(r), &Expr::Const(n.clone())),
+                            &format!("pop_{end} from `{}`", r.0),
+                            &n.to_string()
+                        ),
+                        r.cvt(),
+                    ),
+                    VariableOrLiteral::Variable(x) => format!(
+                        "{0}{spaces}{2} = {1}.{end}();\n{spaces}{1}.pop_{end}();\n",
+                        ctx.expect_eq(
+                            &spaces,
+                            &x.cvt(),
+                            &ctx.zero(x),
+                            &format!("pop_{end} into `{}`", x.0),
+                            if ctx.zero(x) == "0" { "0" } else { "{}" }
+                        ),
+                        r.cvt(),
+                        x.cvt(),
+                    ),
+                }
+            }
+            Self::IndexedSwap(Place(x, is), l, r) => {
+                let (mut l_is, mut r_is) = (is.clone(), is.clone());
+                l_is.push_back(l.clone());
+                r_is.push_back(r.clone());
+                format!(
+                    "{spaces}swap({}, {});\n",
+                    cvt_index(x, &l_is, ctx),
+                    cvt_index(x, &r_is, ctx),
+                )
+            }
+            Self::Rotate(x, rotation, e) => {
+                let f = match rotation {
+                    Rotation::Left => "rotl",
+                    Rotation::Right => "rotr",
+                };
+                format!("{spaces}{f}({}, {});\n", x.cvt_in(ctx), e.cvt_in(ctx))
+            }
+            Self::Complement(x) => format!("{spaces}complement({});\n", x.cvt_in(ctx)),
+            Self::Reverse(x, None) => {
+                format!("{spaces}std::reverse({0}.begin(), {0}.end());\n", x.cvt())
+            }
+            Self::Reverse(x, Some((from, to))) => match ctx.options.index {
+                IndexPolicy::Unchecked => format!(
+                    "{spaces}reverse_range({}, {}, {});\n",
+                    x.cvt(),
+                    from.cvt_in(ctx),
+                    to.cvt_in(ctx)
                 ),
-                VariableOrLiteral::Variable(x) => format!(
-                    "{spaces}assert({1} == 0);\n{spaces}{1} = {0}.back();\n{spaces}{0}.pop_back();\n",
-                    r.cvt(),
-                    x.cvt()
+                IndexPolicy::Checked => format!(
+                    "{spaces}reverse_range_checked({0}, {1}, {2}, \"{0}\", {3});\n",
+                    x.cvt(),
+                    from.cvt_in(ctx),
+                    to.cvt_in(ctx),
+                    ctx.site()
                 ),
             },
-            Self::IndexedSwap(x, l, r) => format!(
-                "{spaces}swap(index({0}, {1}), index({0}, {2}));\n",
-                x.cvt(),
-                l.cvt(),
-                r.cvt(),
-            ),
-            Self::LocalDelocal(tx_l, e_l, s, tx_r, e_r) => format!(
-                "{spaces}{{\n{more_spaces}{} = {};\n{}{more_spaces}assert({} == {});\n{spaces}}}\n",
-                tx_l.cvt(),
-                e_l.cvt(),
-                s.cvt_ind(depth + 1),
-                tx_r.1.cvt(),
-                e_r.cvt()
+            Self::InvlBlock(s, i) => s.cvt_ind(ctx) + &i.cvt_ind(ctx) + &s.flip().cvt_ind(ctx),
+            Self::Negate(x) => format!("{spaces}negate({});\n", x.cvt_in(ctx)),
+            Self::Cswap(c, l, r) => format!(
+                "{spaces}cswap({}, {}, {});\n",
+                c.cvt_in(ctx),
+                l.cvt_in(ctx),
+                r.cvt_in(ctx)
             ),
+            Self::LocalDelocal(tx_l, e_l, s, tx_r, e_r) => {
+                let mut types = ctx.types.clone();
+                types.insert(tx_l.1.clone(), tx_l.0.clone());
+                let inner = Ctx {
+                    types: &types,
+                    ..ctx
+                };
+
+                format!(
+                    "{spaces}{{\n{more_spaces}{} = {};\n{}{}{spaces}}}\n",
+                    tx_l.cvt(),
+                    ctx.init(&tx_l.0, e_l),
+                    s.cvt_ind(inner.deeper()),
+                    inner.expect_eq(
+                        &more_spaces,
+                        &tx_r.1.cvt(),
+                        &inner.value(&tx_r.0, e_r),
+                        &format!("delocal of `{}`", tx_r.1.0),
+                        &e_r.to_string()
+                    ),
+                )
+            }
             either @ (Self::Call(q, args) | Self::Uncall(q, args)) => {
                 let postfix = match either {
                     Self::Call(_, _) => "fwd",
                     Self::Uncall(_, _) => "rev",
                     _ => unreachable!(),
                 };
-                let mut buf = format!("{spaces}{}_{}(", q.cvt(), postfix);
-                buf += &concat(args, ", ", |arg| arg.cvt());
+                // indexed arguments may only alias if neither is mutable
+                let places: Vec<_> = ctx
+                    .params(q)
+                    .iter()
+                    .zip(args)
+                    .filter_map(|(t, arg)| Some((t.r#const, Place::of(arg)?)))
+                    .collect();
+                let mut buf = String::new();
+                for (k, (const_l, l)) in places.iter().enumerate() {
+                    for (const_r, r) in &places[k + 1..] {
+                        if let Alias::When(e) = l.alias(r)
+                            && !(*const_l && *const_r)
+                        {
+                            let what = format!("arguments `{l}` and `{r}` of `{}`", q.0);
+                            buf += &ctx.check(&spaces, &e, false, &what);
+                        }
+                    }
+                }
+
+                let proc_param = Variable(q.0.clone());
+                buf += &match ctx.types.get(&proc_param) {
+                    Some(_) => format!("{spaces}{}.{}(", q.cvt(), postfix),
+                    None => format!("{spaces}{}_{}(", q.cvt(), postfix),
+                };
+                buf += &concat(ctx.params(q).iter().zip(args), ", ", |(t, arg)| {
+                    match (&t.inner, arg) {
+                        // a global proc passed as a proc parameter
+                        (InnerType::Proc(_, _), Expr::Variable(f))
+                            if !ctx.types.contains_key(f) =>
+                        {
+                            format!("{{{0}_fwd, {0}_rev}}", f.cvt())
+                        }
+                        (_, Expr::Variable(_) | Expr::Indexed(_, _)) => arg.cvt_in(ctx),
+                        // bound to a temporary of the parameter type
+                        (t, e) => ctx.init_inner(t, e),
+                    }
+                });
                 buf += ");\n";
                 buf
             }
             Self::Skip => String::new(),
             Self::Print(x) => format!("{spaces}print(\"{0}\", {0});\n", x.0),
+            Self::Assert(assertion, e) => ctx.expect(&spaces, e, true, &assertion.to_string()),
             Self::For(For {
+                index,
                 vars,
                 containers,
+                reversed,
                 statement,
             }) => {
-                let mut buf = format!("{spaces}std::size_t ")
-                    + &concat(containers, ", ", |(x, _)| format!("i_{}{{}}", x.0))
-                    + ";\n";
+                // a field `p.f` is counted by `i_p_f`
+                let counter = |x: &Variable| format!("i_{}", x.0.replace('.', "_"));
 
+                let mut buf = String::new();
                 for (x, i) in containers {
                     let i = match i {
-                        Some(i) => i,
-                        None => continue,
+                        Some(i) if !ctx.options.unchecked => i,
+                        _ => continue,
                     };
 
-                    buf += &format!("{spaces}assert_valid_perm({}, {});\n", x.0, i.0);
+                    buf += &format!(
+                        "{spaces}assert_valid_perm({0}, {1}, \"{0}\", \"{1}\", {2});\n",
+                        x.0,
+                        i.0,
+                        ctx.site()
+                    );
                 }
 
-                buf += &format!(
-                    "{spaces}while ({}) {{\n",
-                    concat(containers.iter().zip(vars), " && ", |((x, _), v)| {
-                        format!("i_{0} + {1} < {0}.size()", x.0, v.len() - 1)
-                    })
-                );
+                // the reversed loop starts past the last complete iteration
+                let (counters, condition) = if *reversed {
+                    let sizes = concat(containers.iter().zip(vars), ", ", |((x, _), v)| {
+                        format!("{}.size() / {}", x.0, v.len())
+                    });
+                    let n = match containers.len() {
+                        1 => sizes,
+                        _ => format!("std::min({{{sizes}}})"),
+                    };
+                    let counters = concat(containers.iter().zip(vars), ", ", |((x, _), v)| {
+                        format!("{} = {n} * {}", counter(x), v.len())
+                    });
+                    let (x, _) = containers.front().expect("unreachable");
+                    (counters, format!("{} > 0", counter(x)))
+                } else {
+                    let counters = concat(containers, ", ", |(x, _)| format!("{}{{}}", counter(x)));
+                    let condition = concat(containers.iter().zip(vars), " && ", |((x, _), v)| {
+                        format!("{} + {} < {}.size()", counter(x), v.len() - 1, x.0)
+                    });
+                    (counters, condition)
+                };
+                buf += &format!("{spaces}for (std::size_t {counters}; {condition};) {{\n");
+
+                let enumerate = |buf: &mut String| {
+                    if let (Some(k), Some((x, _)), Some(vs)) =
+                        (index, containers.front(), vars.front())
+                    {
+                        let i = match vs.len() {
+                            1 => counter(x),
+                            n => format!("{} / {n}", counter(x)),
+                        };
+                        *buf += &format!("{more_spaces}const Int {} = {i};\n", k.cvt());
+                    }
+                };
 
+                if !reversed {
+                    enumerate(&mut buf);
+                }
                 for ((x, i), vs) in containers.iter().zip(vars) {
-                    let i = match i {
-                        None => format!("i_{}++", x.0),
-                        Some(i) => format!("{}[i_{}++]", i.0, x.0),
+                    let i = match (i, reversed) {
+                        (None, false) => format!("{}++", counter(x)),
+                        (None, true) => format!("--{}", counter(x)),
+                        (Some(i), false) => format!("{}[{}++]", i.0, counter(x)),
+                        (Some(i), true) => format!("{}[--{}]", i.0, counter(x)),
                     };
 
+                    let bind = |v: &Variable| {
+                        format!("{more_spaces}auto& {} = index({}, {});\n", v.0, x.0, i)
+                    };
+                    if *reversed {
+                        buf += &concat(vs.iter().rev(), "", bind);
+                    } else {
+                        buf += &concat(vs, "", bind);
+                    }
+                }
+                if *reversed {
+                    enumerate(&mut buf);
+                }
+
+                let mut types = ctx.types.clone();
+                if let Some(k) = index {
+                    types.insert(k.clone(), Type::CONST_INT);
+                }
+                for ((x, _), vs) in containers.iter().zip(vars) {
+                    let t = Type {
+                        r#const: ctx.type_of(x).r#const,
+                        inner: ctx.element(x).clone(),
+                    };
                     for v in vs {
-                        buf += &format!("{more_spaces}auto& {} = index({}, {});\n", v.0, x.0, i);
+                        types.insert(v.clone(), t.clone());
                     }
                 }
+                let inner = Ctx {
+                    types: &types,
+                    ..ctx
+                };
 
-                buf += &format!("\n{}", statement.cvt_ind(depth + 1));
+                buf += &format!("\n{}", statement.cvt_ind(inner.deeper()));
+                buf += &format!("{spaces}}}\n");
+                buf
+            }
+            Self::ForRange(Range {
+                var,
+                from,
+                to,
+                reversed,
+                statement,
+            }) => {
+                let i = var.cvt();
+                let mut buf = if *reversed {
+                    format!(
+                        "{spaces}for (Int {i} = Int({}) - 1, {i}_begin = {}; {i} >= {i}_begin; {i} -= 1) {{\n",
+                        to.cvt_in(ctx),
+                        from.cvt_in(ctx)
+                    )
+                } else {
+                    format!(
+                        "{spaces}for (Int {i} = {}, {i}_end = {}; {i} < {i}_end; {i} += 1) {{\n",
+                        from.cvt_in(ctx),
+                        to.cvt_in(ctx)
+                    )
+                };
+
+                let mut types = ctx.types.clone();
+                types.insert(var.clone(), Type::CONST_INT);
+                let inner = Ctx {
+                    types: &types,
+                    ..ctx
+                };
+
+                buf += &statement.cvt_ind(inner.deeper());
                 buf += &format!("{spaces}}}\n");
                 buf
             }
@@ -401,36 +1158,142 @@ impl CvtInd for Statement {
                 if let Statement::Skip = s_r.as_ref() {
                     format!(
                         "{spaces}if ({}) {{\n{}{spaces}}}\n",
-                        e.cvt(),
-                        s_l.cvt_ind(depth + 1),
+                        e.cvt_in(ctx),
+                        s_l.cvt_ind(ctx.deeper()),
                     )
                 } else {
                     format!(
                         "{spaces}if ({}) {{\n{}{spaces}}} else {{\n{}{spaces}}}\n",
-                        e.cvt(),
-                        s_l.cvt_ind(depth + 1),
-                        s_r.cvt_ind(depth + 1),
+                        e.cvt_in(ctx),
+                        s_l.cvt_ind(ctx.deeper()),
+                        s_r.cvt_ind(ctx.deeper()),
                     )
                 }
             }
-            Self::Sequence(l, r) => format!("{}{}", l.cvt_ind(depth), r.cvt_ind(depth)),
+            Self::Sequence(l, r) => format!("{}{}", l.cvt_ind(ctx), r.cvt_ind(ctx)),
+            Self::Located(location, s) => {
+                let directive = match ctx.options.source_map {
+                    true => format!(
+                        "#line {} {} // column {}",
+                        location.line,
+                        quote(&ctx.options.source),
+                        location.column
+                    ),
+                    false => format!("#line {} {}", location.line, quote(&ctx.options.source)),
+                };
+                let body = s.cvt_ind(Ctx {
+                    location: *location,
+                    ..ctx
+                });
+
+                // every line of this statement gets a directive, while those of nested
+                // statements already follow their own; `prune_directives` drops the repeats
+                let mut buf = String::new();
+                let mut prev = "";
+                for line in body.lines() {
+                    let trimmed = line.trim_start();
+                    if !trimmed.is_empty()
+                        && !trimmed.starts_with("#line ")
+                        && !prev.trim_start().starts_with("#line ")
+                    {
+                        buf += &format!("{}{directive}\n", &line[..line.len() - trimmed.len()]);
+                    }
+                    buf += line;
+                    buf += "\n";
+                    prev = line;
+                }
+                buf
+            }
         }
     }
 }
 
-impl Cvt for Expr {
-    fn cvt(&self) -> String {
+/// `l = r`, parenthesizing compound operands.
+fn equals(l: &Expr, r: &Expr) -> Expr {
+    let wrap = |e: &Expr| match e {
+        Expr::BinOp(_, _, _) => Expr::Wrapped(Rc::new(e.clone())),
+        e => e.clone(),
+    };
+    Expr::BinOp(Rc::new(wrap(l)), BinOp::Equal, Rc::new(wrap(r)))
+}
+
+fn cvt_index(x: &Variable, indices: &LinkedList<Expr>, ctx: Ctx) -> String {
+    let mut buf = x.cvt();
+    let mut path = x.0.to_string();
+    for i in indices {
+        buf = match ctx.options.index {
+            IndexPolicy::Unchecked => format!("index({buf}, {})", i.cvt_in(ctx)),
+            IndexPolicy::Checked => format!(
+                "index_checked({buf}, {}, {}, {})",
+                i.cvt_in(ctx),
+                quote(&path),
+                ctx.site()
+            ),
+        };
+        path += &format!("[{i}]");
+    }
+    buf
+}
+
+impl CvtIn for Place {
+    fn cvt_in(&self, ctx: Ctx) -> String {
+        let Self(x, is) = self;
+        cvt_index(x, is, ctx)
+    }
+}
+
+impl CvtIn for Expr {
+    fn cvt_in(&self, ctx: Ctx) -> String {
         match self {
-            Self::Const(x) => x.to_string(),
+            Self::Const(x) => x.cvt(),
+            Self::Variable(x) if ctx.sizes.contains(x) => format!("Int({})", x.cvt()),
             Self::Variable(x) => x.cvt(),
-            Self::Array(x) => format!("{{{}}}", concat(x.as_ref(), ", ", |item| item.cvt())),
-            Self::Indexed(x, e) => format!("index({}, {})", x.cvt(), e.cvt()),
-            Self::BinOp(l, op, r) => format!("{} {} {}", l.cvt(), op.cvt(), r.cvt()),
-            Self::UnrOp(op, x) => format!("{}{}", op.cvt(), x.cvt()),
+            Self::Array(x) => format!("{{{}}}", concat(x.as_ref(), ", ", |item| item.cvt_in(ctx))),
+            Self::Indexed(x, is) => cvt_index(x, is, ctx),
+            // fixed-width arithmetic wraps, where C++ would promote or overflow
+            Self::BinOp(l, op, r) => match (ctx.scalar_of(self), op) {
+                (
+                    Some(Scalar::Fixed(f)),
+                    BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Remainder,
+                ) => {
+                    let name = match op {
+                        BinOp::Add => "add",
+                        BinOp::Sub => "sub",
+                        BinOp::Mul => "mul",
+                        BinOp::Div => "div",
+                        _ => "rem",
+                    };
+                    format!(
+                        "wrapping_{name}<{}>({}, {})",
+                        f.cvt(),
+                        l.cvt_in(ctx),
+                        r.cvt_in(ctx)
+                    )
+                }
+                _ => format!("{} {} {}", l.cvt_in(ctx), op.cvt(), r.cvt_in(ctx)),
+            },
+            Self::UnrOp(UnrOp::Negative, x) => match ctx.scalar_of(x) {
+                Some(Scalar::Fixed(f)) => {
+                    format!("wrapping_sub<{}>(0, {})", f.cvt(), x.cvt_in(ctx))
+                }
+                _ => format!("{}{}", UnrOp::Negative.cvt(), x.cvt_in(ctx)),
+            },
+            Self::UnrOp(op, x) => format!("{}{}", op.cvt(), x.cvt_in(ctx)),
             Self::Empty(x) => format!("{}.empty()", x.cvt()),
             Self::Nil => "List{}".to_string(),
             Self::Size(x) => format!("{}.size()", x.cvt()),
-            Self::Wrapped(x) => format!("({})", x.cvt()),
+            Self::Wrapped(x) => format!("({})", x.cvt_in(ctx)),
+            Self::Call(q, args) => format!(
+                "{}({})",
+                q.cvt(),
+                concat(ctx.params(q).iter().zip(args.as_ref()), ", ", |(t, e)| {
+                    ctx.init(t, e)
+                })
+            ),
+            Self::Cast(Scalar::Int, x) => format!("Int({})", x.cvt_in(ctx)),
+            Self::Cast(Scalar::Fixed(f), x) => {
+                format!("narrow<{}>({}, {})", f.cvt(), x.cvt_in(ctx), ctx.site())
+            }
         }
     }
 }
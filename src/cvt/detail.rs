@@ -1,4 +1,8 @@
-use crate::parser::detail::{MutOp, Statement};
+use crate::parser::{
+    case::Case,
+    detail::{Expr, MutOp, Rotation, Statement},
+    r#for::{For, Range},
+};
 use std::mem;
 
 pub trait Flip {
@@ -37,11 +41,51 @@ impl Flip for Statement {
                 e_l.clone(),
             ),
             Self::Call(q, args) => Self::Uncall(q.clone(), args.clone()),
+            Self::Rotate(x, rotation, e) => Self::Rotate(x.clone(), rotation.flip(), e.clone()),
+            Self::Complement(x) => Self::Complement(x.clone()),
+            Self::Negate(x) => Self::Negate(x.clone()),
+            Self::Cswap(c, l, r) => Self::Cswap(c.clone(), l.clone(), r.clone()),
+            Self::Reverse(x, range) => Self::Reverse(x.clone(), range.clone()),
             Self::Uncall(q, args) => Self::Call(q.clone(), args.clone()),
             Self::Skip => Self::Skip,
             Self::Print(x) => Self::Print(x.clone()),
-            Self::For(_) | Self::IfThenElse(_, _, _) => unreachable!(),
+            Self::Assert(assertion, e) => Self::Assert(*assertion, e.clone()),
+            Self::ForRange(range) => Self::ForRange(Range {
+                reversed: !range.reversed,
+                statement: Box::new(range.statement.flip()),
+                ..range.clone()
+            }),
+            Self::For(r#for) => Self::For(For {
+                reversed: !r#for.reversed,
+                statement: Box::new(r#for.statement.flip()),
+                ..r#for.clone()
+            }),
+            Self::IfThenElse(e, s_l, s_r) => {
+                Self::IfThenElse(e.clone(), Box::new(s_l.flip()), Box::new(s_r.flip()))
+            }
+            Self::Case(Case {
+                scrutinee,
+                arms,
+                default,
+                exit,
+            }) => Self::Case(Case {
+                scrutinee: exit.clone(),
+                arms: arms.iter().map(|(v, s)| (v.clone(), s.flip())).collect(),
+                default: Box::new(default.flip()),
+                exit: scrutinee.clone(),
+            }),
             Self::Sequence(l, r) => Self::Sequence(Box::new(r.flip()), Box::new(l.flip())),
+            Self::InvlBlock(_, _) => self.clone(),
+            Self::Located(location, s) => Self::Located(*location, Box::new(s.flip())),
+        }
+    }
+}
+
+impl Flip for Rotation {
+    fn flip(&self) -> Self {
+        match self {
+            Self::Left => Self::Right,
+            Self::Right => Self::Left,
         }
     }
 }
@@ -52,6 +96,8 @@ impl Flip for MutOp {
             Self::Add => Self::Sub,
             Self::Sub => Self::Add,
             Self::Xor => Self::Xor,
+            Self::Mul => Self::Div,
+            Self::Div => Self::Mul,
             Self::Swap => Self::Swap,
         }
     }
@@ -67,3 +113,38 @@ where
         acc + mem::replace(&mut d, delim) + &converter(x)
     })
 }
+
+pub fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// The variables, elements and sizes that `e` reads, without duplicates.
+pub fn operands(e: &Expr, buf: &mut Vec<Expr>) {
+    match e {
+        Expr::Const(_) | Expr::Nil => {}
+        Expr::Variable(_) | Expr::Size(_) => {
+            if buf.iter().all(|x| x.to_string() != e.to_string()) {
+                buf.push(e.clone());
+            }
+        }
+        Expr::Empty(x) => operands(&Expr::Variable(x.clone()), buf),
+        Expr::Indexed(_, is) => {
+            for i in is.as_ref() {
+                operands(i, buf);
+            }
+            if buf.iter().all(|x| x.to_string() != e.to_string()) {
+                buf.push(e.clone());
+            }
+        }
+        Expr::Array(l) | Expr::Call(_, l) => {
+            for e in l.as_ref() {
+                operands(e, buf);
+            }
+        }
+        Expr::BinOp(l, _, r) => {
+            operands(l, buf);
+            operands(r, buf);
+        }
+        Expr::UnrOp(_, e) | Expr::Wrapped(e) | Expr::Cast(_, e) => operands(e, buf),
+    }
+}
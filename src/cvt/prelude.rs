@@ -0,0 +1,396 @@
+use crate::{
+    cvt::{IndexPolicy, Options},
+    parser::{
+        case::Case,
+        detail::{
+            Expr, Function, InnerType, MainProc, MutOp, Place, Proc, ProcId, Program, Scalar,
+            Statement, Type, TypedVariable,
+        },
+        r#for::{For, Range},
+    },
+};
+use std::collections::{BTreeSet, LinkedList};
+
+pub const PRELUDE: &str = include_str!("../../prelude.hpp");
+
+const BEGIN: &str = "// begin: ";
+const END: &str = "// end: ";
+
+#[derive(Debug)]
+pub struct Usage {
+    checked_index: bool,
+    unchecked: bool,
+    array: bool,
+    fixed: bool,
+    narrow: bool,
+    muldiv: bool,
+    negate: bool,
+    reverse: bool,
+    cswap: bool,
+    perm: bool,
+    expect: bool,
+    alias: bool,
+    index: bool,
+    proc: bool,
+    calls: BTreeSet<ProcId>,
+}
+
+impl Usage {
+    pub fn new(options: &Options) -> Self {
+        Self {
+            checked_index: options.index == IndexPolicy::Checked,
+            unchecked: options.unchecked,
+            array: false,
+            fixed: false,
+            narrow: false,
+            muldiv: false,
+            negate: false,
+            reverse: false,
+            cswap: false,
+            perm: false,
+            expect: false,
+            alias: false,
+            index: false,
+            proc: false,
+            calls: BTreeSet::new(),
+        }
+    }
+
+    fn calls(&self, name: &str) -> bool {
+        self.calls.iter().any(|id| id.0.as_str() == name)
+    }
+
+    fn wants(&self, section: &str) -> bool {
+        match section {
+            "array" => self.array,
+            "perm" => self.perm && !self.unchecked,
+            "fixed" => self.fixed,
+            "narrow" => self.narrow,
+            "muldiv" => self.muldiv && !self.unchecked,
+            "negate" => self.negate,
+            "cswap" => self.cswap,
+            "reverse" => self.reverse && !self.checked_index,
+            "site" => {
+                self.wants("expect")
+                    || self.wants("checked_index")
+                    || self.wants("narrow")
+                    || self.wants("muldiv")
+                    || self.wants("perm")
+                    || self.wants("iota")
+            }
+            "proc" => self.proc,
+            "expect" => {
+                (self.expect && !self.unchecked)
+                    || self.alias
+                    || (self.wants("iota") && !self.unchecked)
+            }
+            "checked_index" => self.checked_index && (self.index || self.reverse),
+            "iota" => self.calls("iota") || self.calls("indices"),
+            "indices" => self.calls("indices"),
+            x => panic!("unknown prelude section: {x}"),
+        }
+    }
+}
+
+pub trait Uses {
+    fn uses(&self, usage: &mut Usage);
+}
+
+impl Uses for Program {
+    fn uses(&self, usage: &mut Usage) {
+        let Self(main, procs, structs, functions) = self;
+        for s in structs {
+            for field in &s.1 {
+                field.uses(usage);
+            }
+        }
+        main.uses(usage);
+        for proc in procs {
+            proc.uses(usage);
+        }
+        for Function(_, params, result, body) in functions {
+            for param in params {
+                param.uses(usage);
+            }
+            result.uses(usage);
+            body.uses(usage);
+        }
+
+        for proc in procs {
+            let (Proc::Inj(id, _, _, _, _)
+            | Proc::Invl(id, _, _, _, _, _)
+            | Proc::Mat(id, _)
+            | Proc::Conj(id, _, _, _, _)) = proc;
+            usage.calls.remove(id);
+        }
+    }
+}
+
+impl Uses for MainProc {
+    fn uses(&self, usage: &mut Usage) {
+        let Self(decls, statement, invl) = self;
+        for (t_x, e) in decls {
+            t_x.uses(usage);
+            if let Some(e) = e {
+                e.uses(usage);
+            }
+        }
+        statement.uses(usage);
+        invl.uses(usage);
+    }
+}
+
+impl Uses for Proc {
+    fn uses(&self, usage: &mut Usage) {
+        match self {
+            Self::Inj(_, _, args, contract, statement) => {
+                for arg in args {
+                    arg.uses(usage);
+                }
+                contract.requires.uses(usage);
+                contract.ensures.uses(usage);
+                statement.uses(usage);
+            }
+            Self::Invl(_, _, args, contract, statement, invl) => {
+                for arg in args {
+                    arg.uses(usage);
+                }
+                contract.requires.uses(usage);
+                contract.ensures.uses(usage);
+                statement.uses(usage);
+                invl.uses(usage);
+            }
+            Self::Mat(_, _) => {}
+            Self::Conj(_, _, args, q, r) => {
+                for arg in args {
+                    arg.uses(usage);
+                }
+                usage.calls.insert(q.clone());
+                usage.calls.insert(r.clone());
+            }
+        }
+    }
+}
+
+impl Uses for TypedVariable {
+    fn uses(&self, usage: &mut Usage) {
+        self.0.uses(usage);
+    }
+}
+
+impl Uses for Type {
+    fn uses(&self, usage: &mut Usage) {
+        self.inner.uses(usage);
+    }
+}
+
+impl Uses for InnerType {
+    fn uses(&self, usage: &mut Usage) {
+        match self {
+            Self::Scalar(Scalar::Fixed(_)) => usage.fixed = true,
+            Self::Scalar(Scalar::Int) | Self::Struct(_) => {}
+            Self::Array(_, t) => {
+                usage.array = true;
+                t.uses(usage);
+            }
+            Self::List(t) => t.uses(usage),
+            Self::Proc(_, ts) => {
+                usage.proc = true;
+                for t in ts.as_ref() {
+                    t.uses(usage);
+                }
+            }
+            Self::Fn(_, _) => unreachable!(),
+        }
+    }
+}
+
+impl Uses for Expr {
+    fn uses(&self, usage: &mut Usage) {
+        match self {
+            Self::Const(_) | Self::Variable(_) | Self::Empty(_) | Self::Nil | Self::Size(_) => {}
+            Self::Array(l) => {
+                for e in l.as_ref() {
+                    e.uses(usage);
+                }
+            }
+            Self::Indexed(_, is) => {
+                usage.index = true;
+                is.uses(usage);
+            }
+            Self::BinOp(l, _, r) => {
+                l.uses(usage);
+                r.uses(usage);
+            }
+            Self::UnrOp(_, e) | Self::Wrapped(e) => e.uses(usage),
+            Self::Cast(t, e) => {
+                if let Scalar::Fixed(_) = t {
+                    usage.fixed = true;
+                    usage.narrow = true;
+                }
+                e.uses(usage);
+            }
+            Self::Call(_, args) => args.uses(usage),
+        }
+    }
+}
+
+impl Uses for LinkedList<Expr> {
+    fn uses(&self, usage: &mut Usage) {
+        for e in self {
+            e.uses(usage);
+        }
+    }
+}
+
+impl Uses for Place {
+    fn uses(&self, usage: &mut Usage) {
+        if !self.1.is_empty() {
+            usage.index = true;
+            self.1.uses(usage);
+        }
+    }
+}
+
+impl Uses for Statement {
+    fn uses(&self, usage: &mut Usage) {
+        match self {
+            Self::Mut(_, op, e) => {
+                if let MutOp::Mul | MutOp::Div = op {
+                    usage.muldiv = true;
+                }
+                e.uses(usage);
+            }
+            Self::IndexedMut(_, l, op, r) => {
+                if let MutOp::Mul | MutOp::Div = op {
+                    usage.muldiv = true;
+                }
+                usage.index = true;
+                l.uses(usage);
+                r.uses(usage);
+            }
+            Self::Rotate(x, _, e) => {
+                x.uses(usage);
+                e.uses(usage);
+            }
+            Self::Complement(x) => x.uses(usage),
+            Self::Cswap(c, l, r) => {
+                usage.cswap = true;
+                c.uses(usage);
+                l.uses(usage);
+                r.uses(usage);
+            }
+            Self::Reverse(_, Some((from, to))) => {
+                usage.reverse = true;
+                from.uses(usage);
+                to.uses(usage);
+            }
+            Self::Negate(x) => {
+                usage.negate = true;
+                x.uses(usage);
+            }
+            Self::IndexedSwap(x, l, r) => {
+                usage.index = true;
+                x.uses(usage);
+                l.uses(usage);
+                r.uses(usage);
+            }
+            Self::LocalDelocal(tx_l, e_l, s, _, e_r) => {
+                usage.expect = true;
+                tx_l.uses(usage);
+                e_l.uses(usage);
+                s.uses(usage);
+                e_r.uses(usage);
+            }
+            Self::PushFront(_, _)
+            | Self::PushBack(_, _)
+            | Self::PopFront(_, _)
+            | Self::PopBack(_, _) => usage.expect = true,
+            Self::Located(_, s) => s.uses(usage),
+            Self::Assert(_, e) => {
+                usage.expect = true;
+                e.uses(usage);
+            }
+            Self::Call(q, args) | Self::Uncall(q, args) => {
+                usage.calls.insert(q.clone());
+                if args.iter().any(|arg| matches!(arg, Expr::Indexed(_, _))) {
+                    usage.alias = true;
+                }
+                args.uses(usage);
+            }
+            Self::For(For {
+                containers,
+                statement,
+                ..
+            }) => {
+                if containers.iter().any(|(_, i)| i.is_some()) {
+                    usage.perm = true;
+                }
+                statement.uses(usage);
+            }
+            Self::ForRange(Range {
+                from,
+                to,
+                statement,
+                ..
+            }) => {
+                from.uses(usage);
+                to.uses(usage);
+                statement.uses(usage);
+            }
+            Self::IfThenElseFi(e_l, s_l, s_r, e_r) | Self::FromDoLoopUntil(e_l, s_l, s_r, e_r) => {
+                usage.expect = true;
+                e_l.uses(usage);
+                s_l.uses(usage);
+                s_r.uses(usage);
+                e_r.uses(usage);
+            }
+            Self::IfThenElse(e, s_l, s_r) => {
+                e.uses(usage);
+                s_l.uses(usage);
+                s_r.uses(usage);
+            }
+            Self::Case(Case {
+                scrutinee,
+                arms,
+                default,
+                exit,
+            }) => {
+                usage.expect = true;
+                scrutinee.uses(usage);
+                for (v, s) in arms {
+                    v.uses(usage);
+                    s.uses(usage);
+                }
+                default.uses(usage);
+                exit.uses(usage);
+            }
+            Self::Sequence(s_l, s_r) | Self::InvlBlock(s_l, s_r) => {
+                s_l.uses(usage);
+                s_r.uses(usage);
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn inline(usage: &Usage) -> String {
+    let mut buf = String::new();
+    let mut section = None;
+
+    for line in PRELUDE.lines() {
+        if let Some(name) = line.strip_prefix(BEGIN) {
+            section = Some(name);
+        } else if let Some(name) = line.strip_prefix(END) {
+            assert_eq!(section, Some(name), "unbalanced prelude section");
+            section = None;
+        } else if section.is_none_or(|name| usage.wants(name))
+            && !(line.is_empty() && buf.ends_with("\n\n"))
+        {
+            buf += line;
+            buf += "\n";
+        }
+    }
+
+    buf
+}
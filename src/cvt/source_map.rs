@@ -0,0 +1,77 @@
+use crate::cvt::{Options, detail::quote};
+
+#[derive(Debug)]
+struct Mapping {
+    start: usize,
+    end: usize,
+    line: usize,
+    column: usize,
+}
+
+/// The line, file and column of a `#line n "file" // column c` directive.
+fn parse_directive(line: &str) -> Option<(usize, &str, usize)> {
+    let (n, rest) = line.trim_start().strip_prefix("#line ")?.split_once(' ')?;
+    let (file, column) = match rest.split_once(" // column ") {
+        Some((file, column)) => (file, column.parse().ok()?),
+        None => (rest, 0),
+    };
+    Some((n.parse().ok()?, file, column))
+}
+
+/// Maps the lines after each directive for the source to the line and column of its statement.
+/// The range of a directive ends at the next directive, or at a line indented less than its
+/// first line, which closes an enclosing block.
+pub fn source_map(cpp: &str, options: &Options) -> String {
+    let source = quote(&options.source);
+    let mut mappings: Vec<Mapping> = Vec::new();
+    let mut open = false;
+    // the indentation of the first line of the open range
+    let mut depth = None;
+    for (i, line) in cpp.lines().enumerate() {
+        if let Some((n, file, column)) = parse_directive(line) {
+            open = file == source;
+            depth = None;
+            if open {
+                mappings.push(Mapping {
+                    start: i + 2,
+                    end: i + 1,
+                    line: n,
+                    column,
+                });
+            }
+            continue;
+        }
+
+        let trimmed = line.trim_start();
+        if !open || trimmed.is_empty() {
+            continue;
+        }
+        let indent = line.len() - trimmed.len();
+        if indent < *depth.get_or_insert(indent) {
+            open = false;
+        } else if let Some(mapping) = mappings.last_mut() {
+            mapping.end = i + 1;
+        }
+    }
+    mappings.retain(|m| m.start <= m.end);
+
+    let mut buf = format!(
+        "{{\n  \"file\": {},\n  \"source\": {source},\n  \"mappings\": [",
+        quote(&options.output)
+    );
+    let mut delim = "\n";
+    for Mapping {
+        start,
+        end,
+        line,
+        column,
+    } in &mappings
+    {
+        buf += &format!(
+            "{delim}    {{ \"generated\": {{ \"start\": {start}, \"end\": {end} }}, \"source\": {{ \"line\": {line}, \"column\": {column} }} }}"
+        );
+        delim = ",\n";
+    }
+    buf += "\n  ]\n}\n";
+    buf
+}
@@ -4,16 +4,55 @@ mod parser;
 mod tokenizer;
 
 use checker::Checker;
-use cvt::Cvt;
+use cvt::{CvtWith, IndexPolicy, Options, source_map};
 use parser::Parser;
 use std::{env, fs};
 use tokenizer::Tokenizer;
 
+const USAGE: &str = "\
+usage: invl <filename> [options]
+  --inline-prelude   embed the used parts of prelude.hpp into main.cpp
+  --source-map       also write main.cpp.map, a JSON map from main.cpp lines to source lines and columns
+  --index=checked    abort on out-of-bounds indexing
+  --index=unchecked  index without bounds checks (default)
+  --unchecked        omit the reversibility assertions (fi, case, from, delocal, pop),
+                     assert statements, requires/ensures clauses, permutation checks of
+                     for loops and the exactness checks of *= and /=; conversions and
+                     aliasing checks of arguments are kept
+";
+
 fn main() {
-    let filename = env::args().nth(1).expect("input file not specified");
-    let source = fs::read_to_string(filename).expect("come on");
+    let mut options = Options::default();
+    let mut filename = None;
+
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--inline-prelude" => options.inline_prelude = true,
+            "--source-map" => options.source_map = true,
+            "--index=checked" => options.index = IndexPolicy::Checked,
+            "--index=unchecked" => options.index = IndexPolicy::Unchecked,
+            "--unchecked" => options.unchecked = true,
+            "--help" => {
+                print!("{USAGE}");
+                return;
+            }
+            x if x.starts_with("--") => panic!("unknown option: {x}"),
+            _ => filename = Some(arg),
+        }
+    }
+
+    options.source = filename.expect("input file not specified");
+    options.output = "main.cpp".to_string();
+
+    let source = fs::read_to_string(&options.source).expect("come on");
     let tokens = Tokenizer::tokenize(&source);
     let program = Parser::new(tokens).parse_program();
     Checker::check(&program);
-    fs::write("main.cpp", program.cvt()).expect("come on");
+
+    let cpp = program.cvt_with(&options);
+    if options.source_map {
+        let map = source_map(&cpp, &options);
+        fs::write(format!("{}.map", options.output), map).expect("come on");
+    }
+    fs::write(&options.output, cpp).expect("come on");
 }
@@ -1,54 +1,195 @@
+pub mod case;
 pub mod detail;
 pub mod r#for;
+pub mod literal;
 pub mod mat;
 
 use crate::{
     parser::detail::{
-        Expr, MainProc, MutOp, Proc, ProcId, Program, Statement, Type, TypedVariable, UnrOp,
-        Variable,
+        Assertion, Contract, Expr, Function, MainProc, MutOp, Proc, ProcId, Program, Statement,
+        Struct, Type, TypedVariable, UnrOp, Variable,
+    },
+    tokenizer::{
+        TokenList,
+        detail::{Location, Token},
     },
-    tokenizer::{TokenList, detail::Token},
 };
-use detail::{Direction, InnerType, VariableOrLiteral};
-use r#for::For;
+use case::Case;
+use detail::{Direction, InnerType, Place, ProcKind, Rotation, Scalar, Size, VariableOrLiteral};
+use r#for::{For, Range};
+use literal::Literal;
 use mat::InvlMat;
-use std::{collections::LinkedList, rc::Rc};
+use std::{
+    collections::{BTreeMap, LinkedList},
+    mem,
+    rc::Rc,
+};
 
 #[derive(Debug)]
 pub struct Parser {
     tokens: TokenList,
+    structs: BTreeMap<String, Rc<Struct>>,
+    sizes: LinkedList<Variable>,
+    depth: usize,
+    arms: Option<usize>,
 }
 
 impl Parser {
     pub const fn new(tokens: TokenList) -> Self {
-        Self { tokens }
+        Self {
+            tokens,
+            structs: BTreeMap::new(),
+            sizes: LinkedList::new(),
+            depth: 0,
+            arms: None,
+        }
     }
 
     pub fn parse_program(&mut self) -> Program {
+        let mut structs = LinkedList::new();
+        while let Token::Struct = self.seek_front() {
+            structs.push_back(self.parse_struct());
+        }
+
         let p_main = self.parse_main_proc();
 
         let mut procs = LinkedList::new();
+        let mut functions = LinkedList::new();
         while !self.tokens.is_empty() {
-            procs.push_back(self.parse_proc());
+            if let Token::Fn = self.seek_front() {
+                functions.push_back(self.parse_function());
+            } else {
+                procs.push_back(self.parse_proc());
+            }
+        }
+
+        Program(p_main, procs, structs, functions)
+    }
+
+    fn parse_function(&mut self) -> Function {
+        self.pop_assert(Token::Fn);
+        let q = self.parse_proc_id();
+
+        let mut params = LinkedList::new();
+        self.pop_assert(Token::LParen);
+        if !matches!(self.seek_front(), Token::RParen) {
+            loop {
+                let TypedVariable(t, x) = self.parse_typed_variable();
+                let t = Type {
+                    r#const: true,
+                    inner: t.inner,
+                };
+                params.push_back(TypedVariable(t, x));
+                match self.seek_front() {
+                    Token::Comma => {
+                        self.pop_front();
+                    }
+                    Token::RParen => break,
+                    x => panic!("unexpected token: {x:?}"),
+                }
+            }
+        }
+        self.pop_assert(Token::RParen);
+
+        self.pop_assert(Token::Arrow);
+        let result = self.parse_typed_variable();
+        Function(q, params, result, self.parse_statement())
+    }
+
+    fn parse_struct(&mut self) -> Rc<Struct> {
+        self.pop_assert(Token::Struct);
+        let name = match self.pop_front() {
+            Token::Name(x) => x,
+            x => panic!("expected struct name, found {x:?}"),
+        };
+
+        let mut fields = LinkedList::new();
+        while !matches!(self.seek_front(), Token::End) {
+            let TypedVariable(t, f) = self.parse_typed_variable();
+            if let InnerType::Struct(_) = t.inner {
+                panic!(
+                    "field `{}` of struct {name}: nested structs are not supported",
+                    f.0
+                );
+            }
+            if t.r#const {
+                panic!("field `{}` of struct {name} cannot be const", f.0);
+            }
+            if fields.iter().any(|TypedVariable(_, g)| g == &f) {
+                panic!("duplicate field `{}` in struct {name}", f.0);
+            }
+            fields.push_back(TypedVariable(t, f));
         }
+        self.pop_assert(Token::End);
 
-        Program(p_main, procs)
+        let s = Rc::new(Struct(Rc::new(name.clone()), fields));
+        if self.structs.insert(name.clone(), s.clone()).is_some() {
+            panic!("colliding struct names: {name}");
+        }
+        s
+    }
+
+    fn is_struct(&self) -> bool {
+        matches!(self.front(), Some(Token::Name(x)) if self.structs.contains_key(x))
     }
 
     fn parse_inner_type(&mut self) -> InnerType {
         match self.pop_front() {
-            Token::Int => InnerType::Int,
-            Token::List => InnerType::List,
+            Token::Int => InnerType::Scalar(Scalar::Int),
+            Token::Fixed(x) => InnerType::Scalar(Scalar::Fixed(x)),
+            Token::List => {
+                if let Some(Token::LAngleBracket) = self.front() {
+                    self.pop_front();
+                    let t = self.parse_element_type();
+                    self.pop_assert(Token::RAngleBracket);
+                    InnerType::List(Rc::new(t))
+                } else {
+                    InnerType::List(Rc::new(InnerType::Scalar(Scalar::Int)))
+                }
+            }
             Token::Array => {
                 self.pop_assert(Token::LAngleBracket);
-                let c = self.parse_literal();
+                let c = match self.seek_front() {
+                    Token::Name(_) => {
+                        let n = self.parse_variable();
+                        if !self.sizes.contains(&n) {
+                            panic!("undefined size `{}`", n.0);
+                        }
+                        Size::Param(n)
+                    }
+                    _ => {
+                        let c = self.parse_literal();
+                        let c = c
+                            .to_string()
+                            .parse::<usize>()
+                            .unwrap_or_else(|_| panic!("{c} is not a valid array size"));
+                        Size::Const(c)
+                    }
+                };
+                let t = if let Token::Comma = self.seek_front() {
+                    self.pop_front();
+                    self.parse_element_type()
+                } else {
+                    InnerType::Scalar(Scalar::Int)
+                };
                 self.pop_assert(Token::RAngleBracket);
-                InnerType::Array(c as usize)
+                InnerType::Array(c, Rc::new(t))
             }
+            Token::Name(x) => match self.structs.get(&x) {
+                Some(s) => InnerType::Struct(s.clone()),
+                None => panic!("undefined type `{x}`"),
+            },
             x => panic!("expected inner type, found {x:?}"),
         }
     }
 
+    fn parse_element_type(&mut self) -> InnerType {
+        match self.parse_inner_type() {
+            InnerType::Struct(s) => panic!("containers of struct {} are not supported", s.0),
+            t => t,
+        }
+    }
+
     fn parse_type(&mut self) -> Type {
         if let Token::Const = self.seek_front() {
             self.pop_front();
@@ -64,7 +205,7 @@ impl Parser {
         }
     }
 
-    fn parse_literal(&mut self) -> i32 {
+    fn parse_literal(&mut self) -> Literal {
         match self.pop_front() {
             Token::Literal(x) => x,
             Token::Minus => match self.pop_front() {
@@ -75,6 +216,11 @@ impl Parser {
         }
     }
 
+    fn mat_entry(x: Literal) -> i32 {
+        x.to_i32()
+            .unwrap_or_else(|| panic!("{x} is too large for an invl_mat entry"))
+    }
+
     fn parse_variable(&mut self) -> Variable {
         match self.pop_front() {
             Token::Name(x) => Variable::new(x),
@@ -82,6 +228,21 @@ impl Parser {
         }
     }
 
+    fn parse_indices(&mut self) -> LinkedList<Expr> {
+        let mut indices = LinkedList::new();
+        while let Some(Token::LBracket) = self.front() {
+            self.pop_front();
+            indices.push_back(self.parse_expr(0));
+            self.pop_assert(Token::RBracket);
+        }
+        indices
+    }
+
+    fn parse_place(&mut self) -> Place {
+        let x = self.parse_variable();
+        Place(x, self.parse_indices())
+    }
+
     fn parse_proc_id(&mut self) -> ProcId {
         match self.pop_front() {
             Token::Name(x) => ProcId::new(x),
@@ -93,6 +254,39 @@ impl Parser {
         TypedVariable(self.parse_type(), self.parse_variable())
     }
 
+    /// A parameter of a proc: a typed variable, or a proc `proc f(int, const list)` that
+    /// accepts any proc of that signature, or `invl f(int)` that accepts only an invl.
+    fn parse_param(&mut self) -> TypedVariable {
+        let kind = match self.seek_front() {
+            Token::Proc => ProcKind::Inj,
+            Token::Invl => ProcKind::Invl,
+            _ => return self.parse_typed_variable(),
+        };
+        self.pop_front();
+        let f = self.parse_variable();
+
+        let mut params = LinkedList::new();
+        self.pop_assert(Token::LParen);
+        if !matches!(self.seek_front(), Token::RParen) {
+            loop {
+                params.push_back(self.parse_type());
+                match self.pop_front() {
+                    Token::Comma => {}
+                    Token::RParen => break,
+                    x => panic!("unexpected token in proc parameter `{}`: {x:?}", f.0),
+                }
+            }
+        } else {
+            self.pop_front();
+        }
+
+        let t = Type {
+            r#const: true,
+            inner: InnerType::Proc(kind, Rc::new(params)),
+        };
+        TypedVariable(t, f)
+    }
+
     fn parse_main_proc(&mut self) -> MainProc {
         self.pop_assert(Token::Invl);
         self.pop_assert(Token::Main);
@@ -103,8 +297,9 @@ impl Parser {
 
         while matches!(
             self.seek_front(),
-            Token::Const | Token::Int | Token::List | Token::Array
-        ) {
+            Token::Const | Token::Int | Token::Fixed(_) | Token::List | Token::Array
+        ) || self.is_struct()
+        {
             let t_x = self.parse_typed_variable();
             if let Token::Equal = self.seek_front() {
                 self.pop_front();
@@ -130,9 +325,9 @@ impl Parser {
         if !matches!(self.seek_front(), Token::RBracket) {
             loop {
                 match self.pop_front() {
-                    Token::Literal(x) => numbers.push(x),
+                    Token::Literal(x) => numbers.push(Self::mat_entry(x)),
                     Token::Minus => match self.pop_front() {
-                        Token::Literal(x) => numbers.push(-x),
+                        Token::Literal(x) => numbers.push(Self::mat_entry(-x)),
                         x => panic!("expected literal, found {x:?}"),
                     },
                     x => panic!("expected literal or minus, found {x:?}"),
@@ -160,13 +355,31 @@ impl Parser {
 
         let q = self.parse_proc_id();
 
-        match self.pop_front() {
+        let mut sizes = LinkedList::new();
+        if let Token::LAngleBracket = self.seek_front() {
+            self.pop_front();
+            loop {
+                let n = self.parse_variable();
+                if sizes.contains(&n) {
+                    panic!("duplicate size `{}` of `{}`", n.0, q.0);
+                }
+                sizes.push_back(n);
+                match self.pop_front() {
+                    Token::Comma => {}
+                    Token::RAngleBracket => break,
+                    x => panic!("unexpected token in sizes: {x:?}"),
+                }
+            }
+        }
+        self.sizes = sizes.clone();
+
+        let proc = match self.pop_front() {
             Token::LParen => {
                 let mut args = LinkedList::new();
 
                 if !matches!(self.seek_front(), Token::RParen) {
                     loop {
-                        args.push_back(self.parse_typed_variable());
+                        args.push_back(self.parse_param());
                         match self.seek_front() {
                             Token::Comma => {
                                 self.pop_front();
@@ -179,9 +392,29 @@ impl Parser {
 
                 self.pop_assert(Token::RParen);
 
+                for n in &sizes {
+                    if args.iter().any(|TypedVariable(_, x)| x == n) {
+                        panic!("parameter `{}` of `{}` shadows its size", n.0, q.0);
+                    }
+                    if !args.iter().any(|TypedVariable(t, _)| t.inner.has_size(n)) {
+                        panic!("size `{}` of `{}` is not used by any parameter", n.0, q.0);
+                    }
+                }
+
                 match either {
-                    Token::Inj => Proc::Inj(q, args, self.parse_statement()),
+                    Token::Inj => {
+                        let contract = self.parse_contract();
+                        Proc::Inj(q, sizes, args, contract, self.parse_statement())
+                    }
+                    Token::Invl if matches!(self.seek_front(), Token::Equal) => {
+                        self.pop_front();
+                        self.pop_assert(Token::Conj);
+                        let conjugator = self.parse_proc_id();
+                        self.pop_assert(Token::By);
+                        Proc::Conj(q, sizes, args, conjugator, self.parse_proc_id())
+                    }
                     Token::Invl => {
+                        let contract = self.parse_contract();
                         let s = if let Token::With = self.seek_front() {
                             Statement::Skip
                         } else {
@@ -189,14 +422,37 @@ impl Parser {
                         };
                         self.pop_assert(Token::With);
                         let i = self.parse_invl();
-                        Proc::Invl(q, args, s, i)
+                        Proc::Invl(q, sizes, args, contract, s, i)
                     }
                     _ => unreachable!(),
                 }
             }
-            Token::LBracket => Proc::Mat(q, self.parse_mat()),
+            Token::LBracket if sizes.is_empty() => Proc::Mat(q, self.parse_mat()),
             x => panic!("expected proc, found {x:?}"),
+        };
+        self.sizes.clear();
+        proc
+    }
+
+    fn parse_contract(&mut self) -> Contract {
+        let mut requires = Statement::Skip;
+        let mut ensures = Statement::Skip;
+        loop {
+            let location = self.seek_location();
+            let (clauses, assertion) = match self.seek_front() {
+                Token::Requires => (&mut requires, Assertion::Requires),
+                Token::Ensures => (&mut ensures, Assertion::Ensures),
+                _ => break,
+            };
+            self.pop_front();
+            let e = self.parse_expr(0);
+            let assert = Statement::Located(location, Box::new(Statement::Assert(assertion, e)));
+            *clauses = match mem::replace(clauses, Statement::Skip) {
+                Statement::Skip => assert,
+                s => Statement::Sequence(Box::new(s), Box::new(assert)),
+            };
         }
+        Contract { requires, ensures }
     }
 
     fn parse_invl(&mut self) -> Statement {
@@ -205,15 +461,34 @@ impl Parser {
                 s @ (Statement::Mut(_, MutOp::Xor | MutOp::Swap, _)
                 | Statement::IndexedMut(_, _, MutOp::Xor | MutOp::Swap, _)
                 | Statement::IndexedSwap(_, _, _)
+                | Statement::Complement(_)
+                | Statement::Negate(_)
+                | Statement::Cswap(_, _, _)
+                | Statement::InvlBlock(_, _)
+                | Statement::Reverse(_, _)
                 | Statement::Call(_, _)
                 | Statement::Uncall(_, _)
                 | Statement::Skip
                 | Statement::Print(_)
-                | Statement::IfThenElse(_, _, _)
-                | Statement::For(_)) => s,
+                | Statement::Assert(_, _)) => s,
+                // a loop or branch is an involution if its body is
+                Statement::IfThenElse(e, l, r) => {
+                    Statement::IfThenElse(e, Box::new(check(*l)), Box::new(check(*r)))
+                }
+                Statement::For(mut r#for) => {
+                    r#for.statement = Box::new(check(*r#for.statement));
+                    Statement::For(r#for)
+                }
+                Statement::ForRange(mut range) => {
+                    range.statement = Box::new(check(*range.statement));
+                    Statement::ForRange(range)
+                }
                 Statement::Sequence(l, r) => {
                     Statement::Sequence(Box::new(check(*l)), Box::new(check(*r)))
                 }
+                Statement::Located(location, s) => {
+                    Statement::Located(location, Box::new(check(*s)))
+                }
                 x => panic!("expected invl, found {x:?}"),
             }
         }
@@ -240,6 +515,20 @@ impl Parser {
         l
     }
 
+    fn parse_containers(&mut self) -> LinkedList<(Variable, Option<Variable>)> {
+        let mut containers = LinkedList::new();
+        self.pop_assert(Token::LParen);
+        loop {
+            containers.push_back(self.parse_maybe_indexed());
+            match self.pop_front() {
+                Token::Comma => {}
+                Token::RParen => break,
+                x => panic!("unexpected token found in param list: {x:?}"),
+            }
+        }
+        containers
+    }
+
     fn parse_maybe_indexed(&mut self) -> (Variable, Option<Variable>) {
         let x = self.parse_variable();
         let i = if let Token::LBracket = self.seek_front() {
@@ -254,49 +543,146 @@ impl Parser {
     }
 
     fn parse_statement(&mut self) -> Statement {
+        let location = self.seek_location();
         let first = match self.pop_front() {
             Token::Name(x) => match self.seek_front() {
                 Token::LBracket => {
-                    self.pop_front();
-                    let e_l = self.parse_expr(0);
-                    self.pop_assert(Token::RBracket);
-                    let op = self.parse_mut_op();
-                    let e_r = self.parse_expr(0);
-                    Statement::IndexedMut(Variable::new(x), e_l, op, e_r)
+                    let indices = self.parse_indices();
+                    if let Some(rotation) = self.parse_rotation() {
+                        let e_r = self.parse_expr(0);
+                        Statement::Rotate(Place(Variable::new(x), indices), rotation, e_r)
+                    } else {
+                        let op = self.parse_mut_op();
+                        let e_r = self.parse_expr(0);
+                        Statement::IndexedMut(Variable::new(x), indices, op, e_r)
+                    }
                 }
                 _ => {
-                    let op = self.parse_mut_op();
-                    let e_r = self.parse_expr(0);
-                    Statement::Mut(Variable::new(x), op, e_r)
+                    if let Some(rotation) = self.parse_rotation() {
+                        let e_r = self.parse_expr(0);
+                        let x = Place(Variable::new(x), LinkedList::new());
+                        Statement::Rotate(x, rotation, e_r)
+                    } else {
+                        let op = self.parse_mut_op();
+                        let e_r = self.parse_expr(0);
+                        Statement::Mut(Variable::new(x), op, e_r)
+                    }
                 }
             },
+            Token::Tilde => Statement::Complement(self.parse_place()),
+            Token::Reverse => {
+                self.pop_assert(Token::LParen);
+                let x = self.parse_variable();
+                let range = if let Token::Comma = self.seek_front() {
+                    self.pop_front();
+                    let from = self.parse_expr(0);
+                    self.pop_assert(Token::Comma);
+                    let to = self.parse_expr(0);
+                    Some((from, to))
+                } else {
+                    None
+                };
+                self.pop_assert(Token::RParen);
+                Statement::Reverse(x, range)
+            }
+            Token::Negate => Statement::Negate(self.parse_place()),
+            Token::Cswap => {
+                self.pop_assert(Token::LParen);
+                let c = self.parse_place();
+                self.pop_assert(Token::Comma);
+                let l = self.parse_place();
+                self.pop_assert(Token::Comma);
+                let r = self.parse_place();
+                self.pop_assert(Token::RParen);
+                Statement::Cswap(c, l, r)
+            }
             Token::If => {
-                let e_l = self.parse_expr(0);
+                let mut conditions = vec![self.parse_expr(0)];
                 self.pop_assert(Token::Then);
-                let s_l = self.parse_statement();
+                let mut branches = vec![self.parse_statement()];
+                while let Token::Elif = self.seek_front() {
+                    self.pop_front();
+                    conditions.push(self.parse_expr(0));
+                    self.pop_assert(Token::Then);
+                    branches.push(self.parse_statement());
+                }
 
+                let mut r#else = match self.seek_front() {
+                    Token::Else => {
+                        self.pop_front();
+                        self.parse_statement()
+                    }
+                    _ => Statement::Skip,
+                };
+
+                // `elif` chains nest: the last branch becomes the `else` of the one before
                 match self.pop_front() {
                     Token::End => {
-                        Statement::IfThenElse(e_l, Box::new(s_l), Box::new(Statement::Skip))
+                        for (e, s) in conditions.into_iter().zip(branches).rev() {
+                            r#else = Statement::IfThenElse(e, Box::new(s), Box::new(r#else));
+                        }
                     }
                     Token::Fi => {
-                        let e_r = self.parse_expr(0);
-                        Statement::IfThenElseFi(e_l, Box::new(s_l), Box::new(Statement::Skip), e_r)
+                        // one exit assertion per condition: `fi e_1 elif e_2 ...`
+                        let mut assertions = vec![self.parse_expr(0)];
+                        while assertions.len() < conditions.len() {
+                            match self.pop_front() {
+                                Token::Elif => assertions.push(self.parse_expr(0)),
+                                x => panic!(
+                                    "expected elif and an exit assertion for each of the {} \
+                                     conditions, found {x:?}",
+                                    conditions.len()
+                                ),
+                            }
+                        }
+                        let branches = conditions.into_iter().zip(branches).zip(assertions);
+                        for ((e_l, s), e_r) in branches.rev() {
+                            r#else =
+                                Statement::IfThenElseFi(e_l, Box::new(s), Box::new(r#else), e_r);
+                        }
+                    }
+                    x => panic!("expected end, fi, elif or else, found {x:?}"),
+                }
+                r#else
+            }
+            Token::Case => {
+                let scrutinee = self.parse_expr(0);
+                self.pop_assert(Token::Of);
+
+                // a `|` at this depth separates the arms instead of being a bitwise or
+                let outer = self.arms.replace(self.depth);
+                let mut arms = LinkedList::new();
+                let mut default = None;
+                loop {
+                    if let Token::Name(x) = self.seek_front()
+                        && x == "_"
+                    {
+                        self.pop_front();
+                        self.pop_assert(Token::Arrow);
+                        default = Some(self.parse_statement());
+                    } else {
+                        let v = self.parse_expr(0);
+                        self.pop_assert(Token::Arrow);
+                        arms.push_back((v, self.parse_statement()));
                     }
-                    Token::Else => {
-                        let s_r = self.parse_statement();
 
-                        match self.pop_front() {
-                            Token::Fi => {
-                                let e_r = self.parse_expr(0);
-                                Statement::IfThenElseFi(e_l, Box::new(s_l), Box::new(s_r), e_r)
-                            }
-                            Token::End => Statement::IfThenElse(e_l, Box::new(s_l), Box::new(s_r)),
-                            x => panic!("expected fi or end, found {x:?}"),
+                    match self.pop_front() {
+                        Token::VerticalBar if default.is_some() => {
+                            panic!("the default arm `_` of a case must be the last one")
                         }
+                        Token::VerticalBar => {}
+                        Token::Fi => break,
+                        x => panic!("expected | or fi after case arm, found {x:?}"),
                     }
-                    x => panic!("expected end, fi or else, bound {x:?}"),
                 }
+                self.arms = outer;
+
+                Statement::Case(Case {
+                    scrutinee,
+                    arms,
+                    default: Box::new(default.unwrap_or(Statement::Skip)),
+                    exit: self.parse_expr(0),
+                })
             }
             Token::From => {
                 let e_l = self.parse_expr(0);
@@ -320,7 +706,7 @@ impl Parser {
                 self.pop_assert(Token::LParen);
 
                 let l = if let Token::Literal(n) = self.seek_front() {
-                    let n = *n;
+                    let n = n.clone();
                     self.pop_front();
                     VariableOrLiteral::Literal(n)
                 } else {
@@ -359,7 +745,7 @@ impl Parser {
 
                 if !matches!(self.seek_front(), Token::RParen) {
                     loop {
-                        args.push_back(self.parse_variable());
+                        args.push_back(self.parse_expr(0));
 
                         if let Token::Comma = self.seek_front() {
                             self.pop_front();
@@ -378,6 +764,7 @@ impl Parser {
                 }
             }
             Token::Skip => Statement::Skip,
+            Token::Assert => Statement::Assert(Assertion::Assert, self.parse_expr(0)),
             Token::Print => {
                 self.pop_assert(Token::LParen);
                 let x = self.parse_variable();
@@ -403,34 +790,69 @@ impl Parser {
 
                 self.pop_assert(Token::In);
 
-                if let Token::LParen = self.seek_front() {
-                    self.pop_front();
-                    loop {
-                        containers.push_back(self.parse_maybe_indexed());
-                        match self.pop_front() {
-                            Token::Comma => {}
-                            Token::RParen => break,
-                            x => panic!("unexpected token found in param list: {x:?}"),
+                let mut index = None;
+                let mut range = None;
+                match self.seek_front() {
+                    Token::LParen => containers = self.parse_containers(),
+                    Token::Enumerate => {
+                        self.pop_front();
+                        containers = self.parse_containers();
+                        match vars.pop_front() {
+                            Some(k) if k.len() == 1 => index = k.front().cloned(),
+                            _ => panic!("expected an index variable for enumerate"),
                         }
                     }
-                } else {
-                    containers.push_back(self.parse_maybe_indexed());
+                    _ => match self.parse_expr(0) {
+                        from if matches!(self.seek_front(), Token::DoubleDot) => {
+                            self.pop_front();
+                            range = Some((from, self.parse_expr(0)));
+                        }
+                        Expr::Variable(x) => containers.push_back((x, None)),
+                        Expr::Indexed(x, is) => match is.front() {
+                            Some(Expr::Variable(i)) if is.len() == 1 => {
+                                containers.push_back((x, Some(i.clone())))
+                            }
+                            _ => panic!("expected a permutation variable to index `{}`", x.0),
+                        },
+                        e => panic!("expected a container or a range, found `{e}`"),
+                    },
                 }
 
-                assert_eq!(vars.len(), containers.len(), "unmatched params");
-
-                let s = self.parse_statement();
-                self.pop_assert(Token::End);
-
-                Statement::For(For {
-                    vars,
-                    containers,
-                    statement: Box::new(s),
-                })
+                if let Some((from, to)) = range {
+                    let var = match vars.pop_front() {
+                        Some(i) if i.len() == 1 && vars.is_empty() => i.front().cloned(),
+                        _ => None,
+                    };
+                    let var = var.expect("expected one variable for a range");
+
+                    let s = self.parse_statement();
+                    self.pop_assert(Token::End);
+
+                    Statement::ForRange(Range {
+                        var,
+                        from,
+                        to,
+                        reversed: false,
+                        statement: Box::new(s),
+                    })
+                } else {
+                    assert_eq!(vars.len(), containers.len(), "unmatched params");
+
+                    let s = self.parse_statement();
+                    self.pop_assert(Token::End);
+
+                    Statement::For(For {
+                        index,
+                        vars,
+                        containers,
+                        reversed: false,
+                        statement: Box::new(s),
+                    })
+                }
             }
             Token::Swap => {
                 self.pop_assert(Token::LParen);
-                let x = self.parse_variable();
+                let x = self.parse_place();
                 self.pop_assert(Token::Comma);
                 let l = self.parse_expr(0);
                 self.pop_assert(Token::Comma);
@@ -439,13 +861,29 @@ impl Parser {
 
                 Statement::IndexedSwap(x, l, r)
             }
+            Token::Invl => {
+                let s = if let Token::With = self.seek_front() {
+                    Statement::Skip
+                } else {
+                    self.parse_statement()
+                };
+                self.pop_assert(Token::With);
+                let i = self.parse_invl();
+                self.pop_assert(Token::End);
+                Statement::InvlBlock(Box::new(s), Box::new(i))
+            }
             x => panic!("expected statement, found {x:?}"),
         };
+        let first = Statement::Located(location, Box::new(first));
 
-        match self.tokens.front() {
+        match self.front() {
+            Some(Token::Invl) if self.starts_invl_block() => {
+                Statement::Sequence(Box::new(first), Box::new(self.parse_statement()))
+            }
             Some(
                 Token::Name(_)
                 | Token::If
+                | Token::Case
                 | Token::From
                 | Token::PushFront
                 | Token::PushBack
@@ -456,8 +894,13 @@ impl Parser {
                 | Token::Uncall
                 | Token::Skip
                 | Token::Print
+                | Token::Assert
                 | Token::For
-                | Token::Swap,
+                | Token::Swap
+                | Token::Tilde
+                | Token::Negate
+                | Token::Reverse
+                | Token::Cswap,
             ) => Statement::Sequence(Box::new(first), Box::new(self.parse_statement())),
             _ => first,
         }
@@ -503,15 +946,39 @@ impl Parser {
                 }
             }
             Token::Name(x) => {
-                if let Some(Token::LBracket) = self.tokens.front() {
+                if let Some(Token::LBracket) = self.front() {
+                    Expr::Indexed(Variable::new(x), Rc::new(self.parse_indices()))
+                } else if let Some(Token::LParen) = self.front() {
                     self.pop_front();
-                    let e = self.parse_expr(0);
-                    self.pop_assert(Token::RBracket);
-                    Expr::Indexed(Variable::new(x), Rc::new(e))
+                    let mut args = LinkedList::new();
+                    if let Token::RParen = self.seek_front() {
+                        self.pop_front();
+                    } else {
+                        loop {
+                            args.push_back(self.parse_expr(0));
+                            match self.pop_front() {
+                                Token::Comma => {}
+                                Token::RParen => break,
+                                x => panic!("unexpected token in arguments: {x:?}"),
+                            }
+                        }
+                    }
+                    Expr::Call(ProcId::new(x), Rc::new(args))
                 } else {
                     Expr::Variable(Variable::new(x))
                 }
             }
+            token @ (Token::Int | Token::Fixed(_)) => {
+                let t = match token {
+                    Token::Int => Scalar::Int,
+                    Token::Fixed(x) => Scalar::Fixed(x),
+                    _ => unreachable!(),
+                };
+                self.pop_assert(Token::LParen);
+                let e = self.parse_expr(0);
+                self.pop_assert(Token::RParen);
+                Expr::Cast(t, Rc::new(e))
+            }
             token @ (Token::Exclamation | Token::Minus) => {
                 let e = self.parse_expr(255);
                 let op = match token {
@@ -525,7 +992,13 @@ impl Parser {
             x => panic!("invalid token: {x:?} {self:?}"),
         };
 
-        while let Some(front) = self.tokens.front() {
+        while let Some(front) = self.front() {
+            if let Token::VerticalBar = front
+                && self.arms == Some(self.depth)
+            {
+                break;
+            }
+
             let op_detail = match front.detail() {
                 Some(x) if x.prec >= min_prec => x,
                 _ => break,
@@ -546,11 +1019,23 @@ impl Parser {
         first
     }
 
+    fn parse_rotation(&mut self) -> Option<Rotation> {
+        let rotation = match self.seek_front() {
+            Token::TripleLAngleBracketEqual => Rotation::Left,
+            Token::TripleRAngleBracketEqual => Rotation::Right,
+            _ => return None,
+        };
+        self.pop_front();
+        Some(rotation)
+    }
+
     fn parse_mut_op(&mut self) -> MutOp {
         match self.pop_front() {
             Token::PlusEqual => MutOp::Add,
             Token::MinusEqual => MutOp::Sub,
             Token::CaretEqual => MutOp::Xor,
+            Token::AsteriskEqual => MutOp::Mul,
+            Token::SlashEqual => MutOp::Div,
             Token::Spaceship => MutOp::Swap,
             x => panic!("expected mut op, found {x:?}"),
         }
@@ -564,10 +1049,40 @@ impl Parser {
     }
 
     fn pop_front(&mut self) -> Token {
-        self.tokens.pop_front().expect("no token left")
+        let token = self.tokens.pop_front().expect("no token left").0;
+        match token {
+            Token::LParen | Token::LBracket => self.depth += 1,
+            Token::RParen | Token::RBracket => self.depth -= 1,
+            _ => {}
+        }
+        token
+    }
+
+    fn front(&self) -> Option<&Token> {
+        self.tokens.front().map(|(x, _)| x)
     }
 
     fn seek_front(&self) -> &Token {
-        self.tokens.front().expect("no token left")
+        self.front().expect("no token left")
+    }
+
+    fn starts_invl_block(&self) -> bool {
+        let mut tokens = self.tokens.iter().skip(1).map(|(x, _)| x);
+        match (tokens.next(), tokens.next()) {
+            (Some(Token::Main), _)
+            | (Some(Token::Name(_)), Some(Token::LParen | Token::LAngleBracket)) => false,
+            // `invl h[1 0; 0 -1]` is an invl_mat, while `invl a[0] <=> b ...` is a block
+            (Some(Token::Name(_)), Some(Token::LBracket)) => !matches!(
+                tokens
+                    .find(|x| matches!(x, Token::RBracket))
+                    .and(tokens.next()),
+                None | Some(Token::Invl | Token::Inj | Token::Fn)
+            ),
+            _ => true,
+        }
+    }
+
+    fn seek_location(&self) -> Location {
+        self.tokens.front().expect("no token left").1
     }
 }
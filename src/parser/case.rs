@@ -0,0 +1,12 @@
+use crate::parser::{Expr, Statement};
+use std::collections::LinkedList;
+
+/// Runs the first arm whose value equals `scrutinee`, or `default` if there is none; `exit`
+/// must then equal the value of the arm that ran, or none of the values after `default`.
+#[derive(Debug, Clone)]
+pub struct Case {
+    pub scrutinee: Expr,
+    pub arms: LinkedList<(Expr, Statement)>,
+    pub default: Box<Statement>,
+    pub exit: Expr,
+}
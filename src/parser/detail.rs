@@ -1,19 +1,87 @@
-use crate::parser::{r#for::For, mat::InvlMat};
-use std::{collections::LinkedList, rc::Rc};
+use crate::{
+    parser::{
+        case::Case,
+        r#for::{For, Range},
+        literal::Literal,
+        mat::InvlMat,
+    },
+    tokenizer::detail::Location,
+};
+use std::{
+    collections::LinkedList,
+    fmt::{self, Display},
+    mem,
+    rc::Rc,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
-pub enum InnerType {
+pub struct Fixed {
+    pub signed: bool,
+    pub bits: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scalar {
     Int,
-    Array(usize),
-    List,
+    Fixed(Fixed),
+}
+
+/// The size of an array: a constant, or a size parameter `N` of a generic proc.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Size {
+    Const(usize),
+    Param(Variable),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProcKind {
+    Inj,
+    Invl,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InnerType {
+    Scalar(Scalar),
+    Array(Size, Rc<InnerType>),
+    List(Rc<InnerType>),
+    Struct(Rc<Struct>),
+    Proc(ProcKind, Rc<LinkedList<Type>>),
+    /// A `fn` in the scope of the checker, which cannot be declared or passed.
+    Fn(Rc<LinkedList<Type>>, Scalar),
+}
+
+impl InnerType {
+    /// The type of an element `x[i]` if this is a container.
+    pub fn element(&self) -> Option<&Self> {
+        match self {
+            Self::Array(_, t) | Self::List(t) => Some(t),
+            Self::Scalar(_) | Self::Struct(_) | Self::Proc(_, _) | Self::Fn(_, _) => None,
+        }
+    }
+
+    pub fn has_size(&self, n: &Variable) -> bool {
+        match self {
+            Self::Array(Size::Param(m), _) if m == n => true,
+            Self::Array(_, t) | Self::List(t) => t.has_size(n),
+            // sizes cannot be inferred from a proc argument
+            Self::Scalar(_) | Self::Struct(_) | Self::Proc(_, _) | Self::Fn(_, _) => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Type {
     pub r#const: bool,
     pub inner: InnerType,
 }
 
+impl Type {
+    pub const CONST_INT: Self = Self {
+        r#const: true,
+        inner: InnerType::Scalar(Scalar::Int),
+    };
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum BinOp {
     Add,
@@ -45,9 +113,81 @@ pub enum MutOp {
     Add,
     Sub,
     Xor,
+    Mul,
+    Div,
     Swap,
 }
 
+/// A variable or an element `x[i][j]...` of a (nested) container.
+#[derive(Debug, Clone)]
+pub struct Place(pub Variable, pub LinkedList<Expr>);
+
+impl Display for Place {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.0)?;
+        for i in &self.1 {
+            write!(f, "[{i}]")?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether two places refer to overlapping storage.
+#[derive(Debug)]
+pub enum Alias {
+    Never,
+    Always,
+    /// When the condition holds at run time.
+    When(Expr),
+}
+
+impl Place {
+    /// A variable or an indexed element passed as an argument.
+    pub fn of(e: &Expr) -> Option<Self> {
+        match e {
+            Expr::Variable(x) => Some(Self(x.clone(), LinkedList::new())),
+            Expr::Indexed(x, is) => Some(Self(x.clone(), is.as_ref().clone())),
+            _ => None,
+        }
+    }
+
+    /// `a[i][j]` and `a[k]` overlap if `i = k`; the shorter place contains the longer.
+    pub fn alias(&self, other: &Self) -> Alias {
+        if self.0 != other.0 {
+            return Alias::Never;
+        }
+
+        let wrap = |e: &Expr| match e {
+            Expr::BinOp(_, _, _) => Expr::Wrapped(Rc::new(e.clone())),
+            e => e.clone(),
+        };
+        let mut condition = None;
+        for (i, j) in self.1.iter().zip(&other.1) {
+            match (i, j) {
+                _ if i.to_string() == j.to_string() => {}
+                (Expr::Const(_), Expr::Const(_)) => return Alias::Never,
+                _ => {
+                    let eq = Expr::BinOp(Rc::new(wrap(i)), BinOp::Equal, Rc::new(wrap(j)));
+                    condition = Some(match condition {
+                        None => eq,
+                        Some(c) => Expr::BinOp(Rc::new(c), BinOp::LogicalAnd, Rc::new(eq)),
+                    });
+                }
+            }
+        }
+        match condition {
+            None => Alias::Always,
+            Some(c) => Alias::When(c),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rotation {
+    Left,
+    Right,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Variable(pub Rc<String>);
 
@@ -67,11 +207,43 @@ impl ProcId {
 }
 
 #[derive(Debug)]
-pub struct Program(pub MainProc, pub LinkedList<Proc>);
+pub struct Program(
+    pub MainProc,
+    pub LinkedList<Proc>,
+    pub LinkedList<Rc<Struct>>,
+    pub LinkedList<Function>,
+);
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct TypedVariable(pub Type, pub Variable);
 
+impl TypedVariable {
+    /// The variable itself followed by `x.f` for each field if `x` is a struct.
+    pub fn bindings(&self) -> Vec<(Variable, Type)> {
+        let Self(t, x) = self;
+        let mut bindings = vec![(x.clone(), t.clone())];
+        if let InnerType::Struct(s) = &t.inner {
+            for TypedVariable(t_f, f) in &s.1 {
+                let t_f = Type {
+                    r#const: t.r#const,
+                    inner: t_f.inner.clone(),
+                };
+                bindings.push((Variable::new(format!("{}.{}", x.0, f.0)), t_f));
+            }
+        }
+        bindings
+    }
+}
+
+#[derive(Debug)]
+pub struct Struct(pub Rc<String>, pub LinkedList<TypedVariable>);
+
+impl PartialEq for Struct {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
 #[derive(Debug)]
 pub struct MainProc(
     pub LinkedList<(TypedVariable, Option<Expr>)>,
@@ -79,52 +251,293 @@ pub struct MainProc(
     pub Statement,
 );
 
+/// `fn f(params) -> int r body`: runs `body` from `r = 0` and returns `r`, without modifying
+/// its parameters, which are all `const`.
+#[derive(Debug)]
+pub struct Function(
+    pub ProcId,
+    pub LinkedList<TypedVariable>,
+    pub TypedVariable,
+    pub Statement,
+);
+
+/// The `requires` and `ensures` clauses of a proc as `assert` statements, checked on entry
+/// and exit of `_fwd` and the other way around in `_rev`.
+#[derive(Debug)]
+pub struct Contract {
+    pub requires: Statement,
+    pub ensures: Statement,
+}
+
 #[derive(Debug)]
 pub enum Proc {
-    Inj(ProcId, LinkedList<TypedVariable>, Statement),
-    Invl(ProcId, LinkedList<TypedVariable>, Statement, Statement),
+    Inj(
+        ProcId,
+        LinkedList<Variable>,
+        LinkedList<TypedVariable>,
+        Contract,
+        Statement,
+    ),
+    Invl(
+        ProcId,
+        LinkedList<Variable>,
+        LinkedList<TypedVariable>,
+        Contract,
+        Statement,
+        Statement,
+    ),
     Mat(ProcId, InvlMat),
+    Conj(
+        ProcId,
+        LinkedList<Variable>,
+        LinkedList<TypedVariable>,
+        ProcId,
+        ProcId,
+    ),
+}
+
+impl Proc {
+    pub fn id(&self) -> &ProcId {
+        match self {
+            Self::Inj(id, _, _, _, _)
+            | Self::Invl(id, _, _, _, _, _)
+            | Self::Mat(id, _)
+            | Self::Conj(id, _, _, _, _) => id,
+        }
+    }
+
+    pub fn kind(&self) -> ProcKind {
+        match self {
+            Self::Inj(_, _, _, _, _) => ProcKind::Inj,
+            Self::Invl(_, _, _, _, _, _) | Self::Mat(_, _) | Self::Conj(_, _, _, _, _) => {
+                ProcKind::Invl
+            }
+        }
+    }
+
+    /// The types of the parameters; those of an `invl_mat` are `int`s, `const` for the
+    /// rows it leaves unchanged.
+    pub fn signature(&self) -> LinkedList<Type> {
+        match self {
+            Self::Inj(_, _, params, _, _)
+            | Self::Invl(_, _, params, _, _, _)
+            | Self::Conj(_, _, params, _, _) => {
+                params.iter().map(|TypedVariable(t, _)| t.clone()).collect()
+            }
+            Self::Mat(_, mat) => (0..mat.size)
+                .map(|i| Type {
+                    r#const: mat.nop(i),
+                    inner: InnerType::Scalar(Scalar::Int),
+                })
+                .collect(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum VariableOrLiteral {
     Variable(Variable),
-    Literal(i32),
+    Literal(Literal),
 }
 
-#[derive(Debug)]
+/// What an `assert` statement checks, for its message.
+#[derive(Debug, Clone, Copy)]
+pub enum Assertion {
+    Assert,
+    Requires,
+    Ensures,
+}
+
+#[derive(Debug, Clone)]
 pub enum Statement {
     Mut(Variable, MutOp, Expr),
-    IndexedMut(Variable, Expr, MutOp, Expr),
+    IndexedMut(Variable, LinkedList<Expr>, MutOp, Expr),
     IfThenElseFi(Expr, Box<Statement>, Box<Statement>, Expr),
     FromDoLoopUntil(Expr, Box<Statement>, Box<Statement>, Expr),
-    IndexedSwap(Variable, Expr, Expr),
+    IndexedSwap(Place, Expr, Expr),
+    Rotate(Place, Rotation, Expr),
+    Complement(Place),
+    Negate(Place),
+    Cswap(Place, Place, Place),
+    Reverse(Variable, Option<(Expr, Expr)>),
     PushFront(VariableOrLiteral, Variable),
     PushBack(VariableOrLiteral, Variable),
     PopFront(VariableOrLiteral, Variable),
     PopBack(VariableOrLiteral, Variable),
     LocalDelocal(TypedVariable, Expr, Box<Statement>, TypedVariable, Expr),
-    Call(ProcId, LinkedList<Variable>),
-    Uncall(ProcId, LinkedList<Variable>),
+    Call(ProcId, LinkedList<Expr>),
+    Uncall(ProcId, LinkedList<Expr>),
     Skip,
     Print(Variable),
+    Assert(Assertion, Expr),
     For(For),
+    ForRange(Range),
+    Case(Case),
     IfThenElse(Expr, Box<Statement>, Box<Statement>),
     Sequence(Box<Statement>, Box<Statement>),
+    InvlBlock(Box<Statement>, Box<Statement>),
+    Located(Location, Box<Statement>),
 }
 
 #[derive(Debug, Clone)]
 pub enum Expr {
-    Const(i32),
+    Const(Literal),
     Variable(Variable),
     Array(Rc<LinkedList<Expr>>),
-    Indexed(Variable, Rc<Expr>),
+    Indexed(Variable, Rc<LinkedList<Expr>>),
     BinOp(Rc<Expr>, BinOp, Rc<Expr>),
     UnrOp(UnrOp, Rc<Expr>),
     Empty(Variable),
     Nil,
     Size(Variable),
     Wrapped(Rc<Expr>),
+    Cast(Scalar, Rc<Expr>),
+    Call(ProcId, Rc<LinkedList<Expr>>),
+}
+
+impl Display for Fixed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", if self.signed { 'i' } else { 'u' }, self.bits)
+    }
+}
+
+impl Display for Scalar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Int => write!(f, "int"),
+            Self::Fixed(x) => write!(f, "{x}"),
+        }
+    }
+}
+
+impl Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Const(n) => write!(f, "{n}"),
+            Self::Param(n) => write!(f, "{}", n.0),
+        }
+    }
+}
+
+impl Display for InnerType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Scalar(s) => write!(f, "{s}"),
+            Self::Array(n, t) if **t == Self::Scalar(Scalar::Int) => write!(f, "array<{n}>"),
+            Self::Array(n, t) => write!(f, "array<{n}, {t}>"),
+            Self::List(t) if **t == Self::Scalar(Scalar::Int) => write!(f, "list"),
+            Self::List(t) => write!(f, "list<{t}>"),
+            Self::Struct(s) => write!(f, "{}", s.0),
+            Self::Proc(kind, params) => {
+                let mut delim = "";
+                write!(f, "{kind}(")?;
+                for t in params.as_ref() {
+                    let r#const = if t.r#const { "const " } else { "" };
+                    write!(f, "{}{const}{}", mem::replace(&mut delim, ", "), t.inner)?;
+                }
+                write!(f, ")")
+            }
+            Self::Fn(params, result) => {
+                let mut delim = "";
+                write!(f, "fn(")?;
+                for t in params.as_ref() {
+                    write!(f, "{}{}", mem::replace(&mut delim, ", "), t.inner)?;
+                }
+                write!(f, ") -> {result}")
+            }
+        }
+    }
+}
+
+impl Display for ProcKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Inj => write!(f, "proc"),
+            Self::Invl => write!(f, "invl"),
+        }
+    }
+}
+
+impl Display for Assertion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Assert => write!(f, "assert"),
+            Self::Requires => write!(f, "requires"),
+            Self::Ensures => write!(f, "ensures"),
+        }
+    }
+}
+
+impl Display for BinOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            Self::Add => "+",
+            Self::Sub => "-",
+            Self::Xor => "^",
+            Self::Mul => "*",
+            Self::Div => "/",
+            Self::Remainder => "%",
+            Self::BitwiseAnd => "&",
+            Self::BitwiseOr => "|",
+            Self::LogicalAnd => "&&",
+            Self::LogicalOr => "||",
+            Self::LessThan => "<",
+            Self::GreaterThan => ">",
+            Self::Equal => "=",
+            Self::NotEqual => "!=",
+            Self::LessEqual => "<=",
+            Self::GreaterEqual => ">=",
+        };
+        write!(f, "{op}")
+    }
+}
+
+impl Display for UnrOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Negative => write!(f, "-"),
+            Self::Not => write!(f, "!"),
+        }
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Const(x) => write!(f, "{x}"),
+            Self::Variable(x) => write!(f, "{}", x.0),
+            Self::Array(l) => {
+                let mut delim = "";
+                write!(f, "[")?;
+                for e in l.as_ref() {
+                    write!(f, "{}{e}", mem::replace(&mut delim, ", "))?;
+                }
+                write!(f, "]")
+            }
+            Self::Indexed(x, is) => {
+                write!(f, "{}", x.0)?;
+                for i in is.as_ref() {
+                    write!(f, "[{i}]")?;
+                }
+                Ok(())
+            }
+            Self::BinOp(l, op, r) => write!(f, "{l} {op} {r}"),
+            Self::UnrOp(op, e) => write!(f, "{op}{e}"),
+            Self::Empty(x) => write!(f, "empty({})", x.0),
+            Self::Nil => write!(f, "nil"),
+            Self::Size(x) => write!(f, "size({})", x.0),
+            Self::Wrapped(e) => write!(f, "({e})"),
+            Self::Cast(t, e) => write!(f, "{t}({e})"),
+            Self::Call(q, args) => {
+                let mut delim = "";
+                write!(f, "{}(", q.0)?;
+                for e in args.as_ref() {
+                    write!(f, "{}{e}", mem::replace(&mut delim, ", "))?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -1,9 +1,22 @@
-use crate::parser::{Statement, Variable};
+use crate::parser::{Expr, Statement, Variable};
 use std::collections::LinkedList;
 
-#[derive(Debug)]
+/// Iterates the containers in lockstep, from the last complete iteration if reversed.
+#[derive(Debug, Clone)]
 pub struct For {
+    pub index: Option<Variable>,
     pub vars: LinkedList<LinkedList<Variable>>,
     pub containers: LinkedList<(Variable, Option<Variable>)>,
+    pub reversed: bool,
+    pub statement: Box<Statement>,
+}
+
+/// `for i in from..to`, counting down from `to - 1` if reversed.
+#[derive(Debug, Clone)]
+pub struct Range {
+    pub var: Variable,
+    pub from: Expr,
+    pub to: Expr,
+    pub reversed: bool,
     pub statement: Box<Statement>,
 }
@@ -0,0 +1,97 @@
+use std::{
+    fmt::{Display, Formatter},
+    ops::Neg,
+    str::FromStr,
+};
+
+/// An integer literal of unbounded size, kept as normalized decimal digits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Literal {
+    negative: bool,
+    digits: Vec<u8>,
+}
+
+impl Literal {
+    pub fn to_i32(&self) -> Option<i32> {
+        self.to_string().parse().ok()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.digits == [0]
+    }
+
+    pub fn is_odd(&self) -> bool {
+        self.digits.last().is_some_and(|d| d % 2 == 1)
+    }
+
+    fn from_digits(negative: bool, mut digits: Vec<u8>) -> Self {
+        while digits.len() > 1 && digits[0] == 0 {
+            digits.remove(0);
+        }
+
+        Self {
+            negative: negative && digits != [0],
+            digits,
+        }
+    }
+
+    fn mul_add(digits: &mut Vec<u8>, radix: u32, x: u32) {
+        let mut carry = x;
+        for d in digits.iter_mut().rev() {
+            let v = *d as u32 * radix + carry;
+            *d = (v % 10) as u8;
+            carry = v / 10;
+        }
+        while carry > 0 {
+            digits.insert(0, (carry % 10) as u8);
+            carry /= 10;
+        }
+    }
+}
+
+impl FromStr for Literal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (radix, body) = match s.get(..2) {
+            Some("0x" | "0X") => (16, &s[2..]),
+            Some("0b" | "0B") => (2, &s[2..]),
+            Some("0o" | "0O") => (8, &s[2..]),
+            _ => (10, s),
+        };
+
+        if body.is_empty() || body.starts_with('_') || body.ends_with('_') || body.contains("__") {
+            return Err(format!("{s} is not a valid int"));
+        }
+
+        let mut digits = vec![0];
+        for c in body.chars().filter(|&c| c != '_') {
+            let x = c
+                .to_digit(radix)
+                .ok_or_else(|| format!("{s} is not a valid int"))?;
+            Self::mul_add(&mut digits, radix, x);
+        }
+
+        Ok(Self::from_digits(false, digits))
+    }
+}
+
+impl Neg for Literal {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::from_digits(!self.negative, self.digits)
+    }
+}
+
+impl Display for Literal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.negative {
+            write!(f, "-")?;
+        }
+        for d in &self.digits {
+            write!(f, "{d}")?;
+        }
+        Ok(())
+    }
+}
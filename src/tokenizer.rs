@@ -1,33 +1,25 @@
 pub mod detail;
 mod machine;
 
-use detail::{IsSymbol, Token, TokenType};
+use detail::{IsSymbol, Location, Token, TokenType};
 use machine::Machine;
 use std::collections::LinkedList;
 
-pub type TokenList = LinkedList<Token>;
+pub type TokenList = LinkedList<(Token, Location)>;
 pub type CharList = LinkedList<char>;
 
 #[derive(Debug)]
 pub struct Tokenizer {
-    source: CharList,
+    source: LinkedList<(char, Location)>,
 }
 
 impl Tokenizer {
     pub fn tokenize(input: &str) -> TokenList {
-        let comments_excluded: String = input
-            .split("//")
-            .enumerate()
-            .map(|(i, x)| {
-                if i == 0 {
-                    x.to_string()
-                } else {
-                    x.lines()
-                        .skip(1)
-                        .fold(String::new(), |acc, x| format!("{acc}\n{x}"))
-                }
-            })
-            .collect();
+        let comments_excluded = input
+            .lines()
+            .map(|x| x.split("//").next().unwrap_or_default())
+            .collect::<Vec<_>>()
+            .join("\n");
 
         Tokenizer::new(&comments_excluded)
             .tokenize_impl(Machine::default())
@@ -35,20 +27,36 @@ impl Tokenizer {
     }
 
     fn new(input: &str) -> Self {
+        let mut location = Location { line: 1, column: 1 };
+
         Self {
-            source: input.chars().collect(),
+            source: input
+                .chars()
+                .map(|x| {
+                    let current = location;
+                    if x == '\n' {
+                        location.line += 1;
+                        location.column = 1;
+                    } else {
+                        location.column += 1;
+                    }
+                    (x, current)
+                })
+                .collect(),
         }
     }
 
     fn tokenize_impl(&mut self, mut machine: Machine) -> Machine {
-        let first = match self.source.pop_front() {
+        let (first, location) = match self.source.pop_front() {
             None => return machine.process_word(),
-            Some(x) if x.is_whitespace() => return self.tokenize_impl(machine.process_word()),
+            Some((x, _)) if x.is_whitespace() => {
+                return self.tokenize_impl(machine.process_word());
+            }
             Some(x) => x,
         };
 
         let push_token = match machine.get_type() {
-            Some(TokenType::Number) => !first.is_ascii_digit(),
+            Some(TokenType::Number) => !(first.is_ascii_alphanumeric() || first == '_'),
             Some(TokenType::Symbol) => !first.is_symbol(),
             Some(TokenType::Text) => first.is_symbol(),
             None => false,
@@ -56,10 +64,10 @@ impl Tokenizer {
 
         if push_token {
             let mut machine = machine.process_word();
-            machine.word.push_back(first);
+            machine.push_char(first, location);
             self.tokenize_impl(machine)
         } else {
-            machine.word.push_back(first);
+            machine.push_char(first, location);
             self.tokenize_impl(machine)
         }
     }
@@ -1,14 +1,24 @@
-use crate::parser::detail::{BinOp, BinOpDetail, Direction};
+use crate::parser::{
+    detail::{BinOp, BinOpDetail, Direction, Fixed},
+    literal::Literal,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     Name(String),
-    Literal(i32),
+    Literal(Literal),
     LBracket,
     RBracket,
     LParen,
     RParen,
     Int,
+    Fixed(Fixed),
     List,
     Array,
     Invl,
@@ -20,6 +30,11 @@ pub enum Token {
     PlusEqual,
     MinusEqual,
     CaretEqual,
+    AsteriskEqual,
+    SlashEqual,
+    TripleLAngleBracketEqual,
+    TripleRAngleBracketEqual,
+    Tilde,
     Asterisk,
     Slash,
     Percent,
@@ -64,6 +79,23 @@ pub enum Token {
     End,
     Const,
     Swap,
+    Negate,
+    Reverse,
+    Cswap,
+    Conj,
+    Proc,
+    By,
+    Struct,
+    Enumerate,
+    DoubleDot,
+    Elif,
+    Case,
+    Of,
+    Arrow,
+    Assert,
+    Requires,
+    Ensures,
+    Fn,
 }
 
 impl Token {
@@ -123,6 +155,7 @@ impl IsSymbol for char {
                 | '>'
                 | '='
                 | '!'
+                | '~'
                 | ';'
         )
     }
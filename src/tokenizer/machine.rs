@@ -1,6 +1,7 @@
+use crate::parser::{detail::Fixed, literal::Literal};
 use crate::tokenizer::{
     CharList, TokenList,
-    detail::{IsSymbol, Token, TokenType},
+    detail::{IsSymbol, Location, Token, TokenType},
 };
 use std::{cell::LazyCell, collections::BTreeMap};
 
@@ -8,6 +9,7 @@ use std::{cell::LazyCell, collections::BTreeMap};
 pub struct Machine {
     pub word: CharList,
     pub tokens: TokenList,
+    start: Location,
 }
 
 impl Machine {
@@ -20,6 +22,12 @@ impl Machine {
             push("(", Token::LParen);
             push(")", Token::RParen);
             push("int", Token::Int);
+            for signed in [false, true] {
+                for bits in [8, 16, 32, 64] {
+                    let fixed = Fixed { signed, bits };
+                    push(&fixed.to_string(), Token::Fixed(fixed));
+                }
+            }
             push("list", Token::List);
             push("array", Token::Array);
             push("invl", Token::Invl);
@@ -31,6 +39,11 @@ impl Machine {
             push("+=", Token::PlusEqual);
             push("-=", Token::MinusEqual);
             push("^=", Token::CaretEqual);
+            push("*=", Token::AsteriskEqual);
+            push("/=", Token::SlashEqual);
+            push("<<<=", Token::TripleLAngleBracketEqual);
+            push(">>>=", Token::TripleRAngleBracketEqual);
+            push("~", Token::Tilde);
             push("*", Token::Asterisk);
             push("/", Token::Slash);
             push("%", Token::Percent);
@@ -75,6 +88,23 @@ impl Machine {
             push("end", Token::End);
             push("const", Token::Const);
             push("swap", Token::Swap);
+            push("negate", Token::Negate);
+            push("reverse", Token::Reverse);
+            push("cswap", Token::Cswap);
+            push("conj", Token::Conj);
+            push("proc", Token::Proc);
+            push("by", Token::By);
+            push("struct", Token::Struct);
+            push("enumerate", Token::Enumerate);
+            push("..", Token::DoubleDot);
+            push("elif", Token::Elif);
+            push("case", Token::Case);
+            push("of", Token::Of);
+            push("->", Token::Arrow);
+            push("assert", Token::Assert);
+            push("requires", Token::Requires);
+            push("ensures", Token::Ensures);
+            push("fn", Token::Fn);
             retval
         });
 
@@ -86,6 +116,23 @@ impl Machine {
             return self.push_and_clear(token.clone());
         }
 
+        // `.` belongs to names like `s.f`, so `0..n` lexes as `0` and `..n`
+        let mut dots = self.word.iter().zip(self.word.iter().skip(1));
+        if let Some(k) = dots.position(|(a, b)| *a == '.' && *b == '.') {
+            let mut right = self.word.split_off(k);
+            let rest = right.split_off(2);
+            if !self.word.is_empty() {
+                let n = self.word.len();
+                self = self.process_word();
+                self.start.column += n;
+            }
+            self.word = right;
+            self = self.process_word();
+            self.start.column += 2;
+            self.word = rest;
+            return self.process_word();
+        }
+
         let token = match self.get_type().expect("empty word") {
             TokenType::Symbol => {
                 let mut right = CharList::new();
@@ -97,17 +144,17 @@ impl Machine {
                     right.push_front(self.word.pop_back().expect("unreachable"));
 
                     if let Some(token) = table.get(&self.word) {
-                        self.tokens.push_back(token.clone());
+                        self.tokens.push_back((token.clone(), self.start));
+                        self.start.column += self.word.len();
                         self.word = right;
                         return self.process_word();
                     }
                 }
             }
             TokenType::Number => {
-                let string = self.stringify();
-                let parsed = match string.parse::<i32>() {
+                let parsed = match self.stringify().parse::<Literal>() {
                     Ok(x) => x,
-                    Err(_) => panic!("{} is not a valid int", string),
+                    Err(e) => panic!("{e}"),
                 };
 
                 Token::Literal(parsed)
@@ -130,12 +177,19 @@ impl Machine {
         })
     }
 
+    pub fn push_char(&mut self, x: char, location: Location) {
+        if self.word.is_empty() {
+            self.start = location;
+        }
+        self.word.push_back(x);
+    }
+
     pub fn take_tokens(self) -> TokenList {
         self.tokens
     }
 
     fn push_and_clear(mut self, token: Token) -> Self {
-        self.tokens.push_back(token);
+        self.tokens.push_back((token, self.start));
         self.word.clear();
         self
     }
@@ -0,0 +1,161 @@
+//! Converts every program under `examples/`, and checks that those under `tests/errors/` are
+//! rejected with the message in their `// error:` line.
+//!
+//! With `INVL_CXX` set to a compiler command, e.g. `INVL_CXX="g++ -std=c++23"`, each example is
+//! also compiled and run, and its output compared with the `.out` file next to it; the programs
+//! under `tests/aborts/` must then fail at run time with the message in their `// abort:` line.
+//! Options for the converter go in an `// options:` line.
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+fn programs(dir: &str) -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(dir);
+    if !dir.exists() {
+        return Vec::new();
+    }
+    let mut programs: Vec<_> = fs::read_dir(&dir)
+        .unwrap_or_else(|e| panic!("{}: {e}", dir.display()))
+        .map(|entry| entry.expect("readable entry").path())
+        .filter(|path| path.extension().is_some_and(|x| x == "invl"))
+        .collect();
+    programs.sort();
+    programs
+}
+
+/// The value of a `// key: value` line among the leading comments of a program.
+fn header(program: &Path, key: &str) -> Option<String> {
+    let source = fs::read_to_string(program).expect("readable program");
+    let prefix = format!("// {key}:");
+    source
+        .lines()
+        .map_while(|line| line.strip_prefix("//").map(|_| line))
+        .find_map(|line| line.strip_prefix(&prefix))
+        .map(|value| value.trim().to_string())
+}
+
+/// A scratch directory for the `main.cpp` of a program.
+fn scratch(program: &Path) -> PathBuf {
+    let stem = program.file_stem().expect("file name").to_string_lossy();
+    let dir = env::temp_dir().join(format!("invl-{}-{stem}", std::process::id()));
+    fs::create_dir_all(&dir).expect("writable temp dir");
+    dir
+}
+
+fn convert(program: &Path, dir: &Path) -> Output {
+    let options = header(program, "options").unwrap_or_default();
+    Command::new(env!("CARGO_BIN_EXE_invl"))
+        .arg(program)
+        .args(options.split_whitespace())
+        .current_dir(dir)
+        .output()
+        .expect("runnable converter")
+}
+
+/// Compiles the `main.cpp` in `dir` with `cxx` and runs it.
+fn compile_and_run(cxx: &str, dir: &Path) -> Output {
+    let mut words = cxx.split_whitespace();
+    let status = Command::new(words.next().expect("compiler"))
+        .args(words)
+        .arg("-I")
+        .arg(env!("CARGO_MANIFEST_DIR"))
+        .args(["main.cpp", "-o", "main"])
+        .current_dir(dir)
+        .status()
+        .expect("runnable compiler");
+    assert!(
+        status.success(),
+        "{}: main.cpp does not compile",
+        dir.display()
+    );
+    Command::new(dir.join("main"))
+        .output()
+        .expect("runnable program")
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn examples() {
+    let cxx = env::var("INVL_CXX").ok();
+    for program in programs("examples") {
+        let dir = scratch(&program);
+        let output = convert(&program, &dir);
+        assert!(
+            output.status.success(),
+            "{}: {}",
+            program.display(),
+            stderr(&output)
+        );
+
+        if let Some(cxx) = &cxx {
+            let output = compile_and_run(cxx, &dir);
+            assert!(
+                output.status.success(),
+                "{}: {}",
+                program.display(),
+                stderr(&output)
+            );
+            let expected = fs::read_to_string(program.with_extension("out"))
+                .unwrap_or_else(|e| panic!("{}: {e}", program.display()));
+            assert_eq!(
+                String::from_utf8_lossy(&output.stdout),
+                expected,
+                "{}",
+                program.display()
+            );
+        }
+        fs::remove_dir_all(&dir).ok();
+    }
+}
+
+#[test]
+fn errors() {
+    for program in programs("tests/errors") {
+        let expected = header(&program, "error")
+            .unwrap_or_else(|| panic!("{}: no `// error:` line", program.display()));
+        let dir = scratch(&program);
+        let output = convert(&program, &dir);
+        fs::remove_dir_all(&dir).ok();
+
+        assert!(
+            !output.status.success() && stderr(&output).contains(&expected),
+            "{}: expected `{expected}`, got {}",
+            program.display(),
+            stderr(&output)
+        );
+    }
+}
+
+#[test]
+fn aborts() {
+    let Ok(cxx) = env::var("INVL_CXX") else {
+        return;
+    };
+    for program in programs("tests/aborts") {
+        let expected = header(&program, "abort")
+            .unwrap_or_else(|| panic!("{}: no `// abort:` line", program.display()));
+        let dir = scratch(&program);
+        let output = convert(&program, &dir);
+        assert!(
+            output.status.success(),
+            "{}: {}",
+            program.display(),
+            stderr(&output)
+        );
+
+        let output = compile_and_run(&cxx, &dir);
+        fs::remove_dir_all(&dir).ok();
+        assert!(
+            !output.status.success() && stderr(&output).contains(&expected),
+            "{}: expected `{expected}`, got {}",
+            program.display(),
+            stderr(&output)
+        );
+    }
+}